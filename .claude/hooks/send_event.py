@@ -30,14 +30,21 @@ except ImportError:
 def send_event_to_server(event_data, server_url='http://localhost:4000/events'):
     """Send event data to the observability server."""
     try:
+        headers = {
+            'Content-Type': 'application/json',
+            'User-Agent': 'Claude-Code-Hook/1.0'
+        }
+        
+        # Authenticate when the server has API_KEY configured
+        api_key = os.getenv('OBSERVABILITY_API_KEY')
+        if api_key:
+            headers['X-Api-Key'] = api_key
+        
         # Prepare the request
         req = urllib.request.Request(
             server_url,
             data=json.dumps(event_data).encode('utf-8'),
-            headers={
-                'Content-Type': 'application/json',
-                'User-Agent': 'Claude-Code-Hook/1.0'
-            }
+            headers=headers
         )
         
         # Send the request
@@ -12,6 +12,7 @@ import type {
   ThemeApiResponse
 } from '../types/theme';
 import { PREDEFINED_THEME_NAMES, COLOR_REGEX, RGBA_REGEX } from '../types/theme';
+import { apiHeaders } from '../utils/api';
 
 // Predefined themes configuration
 const PREDEFINED_THEMES: Record<ThemeName, PredefinedTheme> = {
@@ -537,7 +538,7 @@ export function useThemes() {
   const saveThemeToServer = async (theme: CustomTheme): Promise<void> => {
     const response = await fetch('http://localhost:4000/api/themes', {
       method: 'POST',
-      headers: { 'Content-Type': 'application/json' },
+      headers: apiHeaders({ 'Content-Type': 'application/json' }),
       body: JSON.stringify(theme)
     });
 
@@ -549,7 +550,7 @@ export function useThemes() {
 
   const loadThemesFromServer = async (): Promise<CustomTheme[]> => {
     try {
-      const response = await fetch('http://localhost:4000/api/themes?isPublic=true');
+      const response = await fetch('http://localhost:4000/api/themes?isPublic=true', { headers: apiHeaders() });
       if (!response.ok) return [];
       
       const result: ThemeApiResponse<CustomTheme[]> = await response.json();
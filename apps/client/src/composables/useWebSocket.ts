@@ -1,5 +1,6 @@
 import { ref, onMounted, onUnmounted } from 'vue';
 import type { HookEvent, WebSocketMessage } from '../types';
+import { withApiKey } from '../utils/api';
 
 export function useWebSocket(url: string) {
   const events = ref<HookEvent[]>([]);
@@ -8,13 +9,19 @@ export function useWebSocket(url: string) {
   
   let ws: WebSocket | null = null;
   let reconnectTimeout: number | null = null;
+  // Highest event id received, so a reconnect can ask the server to replay what was missed
+  let lastEventId: number | null = null;
   
   // Get max events from environment variable or use default
   const maxEvents = parseInt(import.meta.env.VITE_MAX_EVENTS_TO_DISPLAY || '100');
   
   const connect = () => {
     try {
-      ws = new WebSocket(url);
+      const connectUrl = withApiKey(new URL(url));
+      if (lastEventId !== null) {
+        connectUrl.searchParams.set('since_id', String(lastEventId));
+      }
+      ws = new WebSocket(connectUrl);
       
       ws.onopen = () => {
         console.log('WebSocket connected');
@@ -27,12 +34,16 @@ export function useWebSocket(url: string) {
           const message: WebSocketMessage = JSON.parse(event.data);
           
           if (message.type === 'initial') {
-            const initialEvents = Array.isArray(message.data) ? message.data : [];
+            const initialEvents: HookEvent[] = Array.isArray(message.data) ? message.data : [];
             // Only keep the most recent events up to maxEvents
             events.value = initialEvents.slice(-maxEvents);
+            lastEventId = initialEvents.reduce((max, e) => Math.max(max, e.id ?? 0), lastEventId ?? 0);
           } else if (message.type === 'event') {
             const newEvent = message.data as HookEvent;
             events.value.push(newEvent);
+            if (newEvent.id !== undefined) {
+              lastEventId = Math.max(lastEventId ?? 0, newEvent.id);
+            }
             
             // Limit events array to maxEvents, removing the oldest when exceeded
             if (events.value.length > maxEvents) {
@@ -0,0 +1,16 @@
+// API key sent with every request when the server runs with API_KEY set.
+// Vite inlines it into the bundle, so anyone who can load the dashboard can read it.
+const apiKey: string | undefined = import.meta.env.VITE_API_KEY || undefined;
+
+// Request headers for the observability server, with X-Api-Key added when configured
+export function apiHeaders(headers: Record<string, string> = {}): Record<string, string> {
+  return apiKey ? { ...headers, 'X-Api-Key': apiKey } : headers;
+}
+
+// Browsers can't set headers on a WebSocket, so /stream takes the key as ?api_key= instead
+export function withApiKey(url: URL): URL {
+  if (apiKey) {
+    url.searchParams.set('api_key', apiKey);
+  }
+  return url;
+}
@@ -0,0 +1,112 @@
+import { describe, test, expect } from 'bun:test';
+import { createHmac } from 'node:crypto';
+import { config } from './config';
+import { authenticate } from './auth';
+import { restoreConfigAfterEach } from './testHelpers';
+
+restoreConfigAfterEach();
+
+function encodeSegment(part: object): string {
+  return Buffer.from(JSON.stringify(part)).toString('base64url');
+}
+
+function signJwt(claims: object, secret: string): string {
+  const unsigned = `${encodeSegment({ alg: 'HS256', typ: 'JWT' })}.${encodeSegment(claims)}`;
+  return `${unsigned}.${createHmac('sha256', secret).update(unsigned).digest('base64url')}`;
+}
+
+function authenticateRequest(path: string, headers: Record<string, string> = {}) {
+  const url = new URL(`http://localhost${path}`);
+  return authenticate(new Request(url, { headers }), url, url.pathname);
+}
+
+describe('API key authentication', () => {
+  test('a matching X-Api-Key is allowed', () => {
+    config.API_KEY = 'test-key';
+    expect(authenticateRequest('/events', { 'X-Api-Key': 'test-key' })).toEqual({ ok: true });
+  });
+  
+  test('a missing key is rejected with 401', () => {
+    config.API_KEY = 'test-key';
+    expect(authenticateRequest('/events')).toEqual({ ok: false, status: 401, error: 'Missing API key' });
+  });
+  
+  test('a wrong key is rejected with 403', () => {
+    config.API_KEY = 'test-key';
+    expect(authenticateRequest('/events', { 'X-Api-Key': 'guess' })).toEqual({ ok: false, status: 403, error: 'Invalid API key' });
+  });
+  
+  test('/health stays public', () => {
+    config.API_KEY = 'test-key';
+    expect(authenticateRequest('/health')).toEqual({ ok: true });
+  });
+  
+  test('stream endpoints accept the key as a query parameter', () => {
+    config.API_KEY = 'test-key';
+    expect(authenticateRequest('/stream?api_key=test-key')).toEqual({ ok: true });
+    expect(authenticateRequest('/events?api_key=test-key').ok).toBe(false);
+  });
+  
+  test('everything is allowed when API_KEY is unset', () => {
+    config.API_KEY = undefined;
+    expect(authenticateRequest('/events')).toEqual({ ok: true });
+  });
+});
+
+describe('JWT authentication', () => {
+  const nowSeconds = () => Math.floor(Date.now() / 1000);
+  
+  function useJwt(): void {
+    config.AUTH_MODE = 'jwt';
+    config.JWT_SECRET = 'test-secret';
+  }
+  
+  test('a valid token is allowed and its claims are returned', () => {
+    useJwt();
+    const token = signJwt({ sub: 'agent-1', scopes: ['events:write'], exp: nowSeconds() + 60 }, 'test-secret');
+    
+    const result = authenticateRequest('/events', { Authorization: `Bearer ${token}` });
+    
+    expect(result.ok).toBe(true);
+    if (result.ok) {
+      expect(result.claims?.sub).toBe('agent-1');
+      expect(result.claims?.scopes).toEqual(['events:write']);
+    }
+  });
+  
+  test('an expired token is rejected with 401', () => {
+    useJwt();
+    const token = signJwt({ sub: 'agent-1', exp: nowSeconds() - 60 }, 'test-secret');
+    
+    expect(authenticateRequest('/events', { Authorization: `Bearer ${token}` }))
+      .toEqual({ ok: false, status: 401, error: 'Token expired' });
+  });
+  
+  test('a token with a tampered payload is rejected with 401', () => {
+    useJwt();
+    const [header, , signature] = signJwt({ sub: 'agent-1' }, 'test-secret').split('.');
+    const tampered = `${header}.${encodeSegment({ sub: 'admin' })}.${signature}`;
+    
+    expect(authenticateRequest('/events', { Authorization: `Bearer ${tampered}` }))
+      .toEqual({ ok: false, status: 401, error: 'Invalid token signature' });
+  });
+  
+  test('a token signed with another secret is rejected with 401', () => {
+    useJwt();
+    const token = signJwt({ sub: 'agent-1' }, 'other-secret');
+    
+    expect(authenticateRequest('/events', { Authorization: `Bearer ${token}` }))
+      .toEqual({ ok: false, status: 401, error: 'Invalid token signature' });
+  });
+  
+  test('a missing token is rejected with 401', () => {
+    useJwt();
+    expect(authenticateRequest('/events')).toEqual({ ok: false, status: 401, error: 'Missing bearer token' });
+  });
+  
+  test('an API key is not accepted in jwt mode', () => {
+    useJwt();
+    config.API_KEY = 'test-key';
+    expect(authenticateRequest('/events', { 'X-Api-Key': 'test-key' }).ok).toBe(false);
+  });
+});
@@ -0,0 +1,115 @@
+import { createHash, createHmac, timingSafeEqual } from 'node:crypto';
+import { config } from './config';
+
+// Routes that stay reachable without credentials so probes, uptime checks and the
+// API docs keep working (the docs are switched off in production when auth is on)
+const PUBLIC_PATHS = new Set(['/', '/ping', '/health', '/health/live', '/health/ready', '/docs', '/openapi.json']);
+
+// Browsers can't set headers on WebSocket or EventSource connections, so these
+// also accept credentials as a query parameter (?api_key= or ?access_token=)
+const STREAM_PATHS = new Set(['/stream', '/events/stream']);
+
+export interface JwtClaims {
+  sub?: string;
+  scopes?: string[];
+  exp?: number;
+  [claim: string]: unknown;
+}
+
+export type AuthResult =
+  | { ok: true; claims?: JwtClaims }
+  | { ok: false; status: 401 | 403; error: string };
+
+export function isAuthEnabled(): boolean {
+  return config.AUTH_MODE === 'jwt' || Boolean(config.API_KEY);
+}
+
+// Compare digests so the check takes the same time regardless of where the keys differ
+function keysMatch(provided: string, expected: string): boolean {
+  const a = createHash('sha256').update(provided).digest();
+  const b = createHash('sha256').update(expected).digest();
+  return timingSafeEqual(a, b);
+}
+
+function decodeSegment(segment: string): any {
+  return JSON.parse(Buffer.from(segment, 'base64url').toString('utf8'));
+}
+
+// Verify an HS256 JWT's signature and expiry
+function verifyJwt(token: string, secret: string): { claims: JwtClaims } | { error: string } {
+  const [header, payload, signature, ...rest] = token.split('.');
+  if (!header || !payload || !signature || rest.length > 0) {
+    return { error: 'Malformed token' };
+  }
+  
+  try {
+    if (decodeSegment(header).alg !== 'HS256') {
+      return { error: 'Unsupported token algorithm' };
+    }
+    
+    const expected = createHmac('sha256', secret).update(`${header}.${payload}`).digest();
+    const actual = Buffer.from(signature, 'base64url');
+    if (actual.length !== expected.length || !timingSafeEqual(actual, expected)) {
+      return { error: 'Invalid token signature' };
+    }
+    
+    const claims = decodeSegment(payload) as JwtClaims;
+    if (typeof claims.exp === 'number' && claims.exp * 1000 <= Date.now()) {
+      return { error: 'Token expired' };
+    }
+    
+    return { claims };
+  } catch (error) {
+    return { error: 'Malformed token' };
+  }
+}
+
+function authenticateApiKey(req: Request, url: URL, pathname: string): AuthResult {
+  if (!config.API_KEY) {
+    return { ok: true };
+  }
+  
+  const provided = req.headers.get('x-api-key')
+    ?? (STREAM_PATHS.has(pathname) ? url.searchParams.get('api_key') : null);
+  
+  if (!provided) {
+    return { ok: false, status: 401, error: 'Missing API key' };
+  }
+  
+  if (!keysMatch(provided, config.API_KEY)) {
+    return { ok: false, status: 403, error: 'Invalid API key' };
+  }
+  
+  return { ok: true };
+}
+
+function authenticateJwt(req: Request, url: URL, pathname: string): AuthResult {
+  const authorization = req.headers.get('authorization');
+  const token = authorization?.startsWith('Bearer ')
+    ? authorization.slice('Bearer '.length).trim()
+    : (STREAM_PATHS.has(pathname) ? url.searchParams.get('access_token') : null);
+  
+  if (!token) {
+    return { ok: false, status: 401, error: 'Missing bearer token' };
+  }
+  
+  // JWT_SECRET presence is enforced at startup when AUTH_MODE=jwt
+  const result = verifyJwt(token, config.JWT_SECRET ?? '');
+  if ('error' in result) {
+    return { ok: false, status: 401, error: result.error };
+  }
+  
+  return { ok: true, claims: result.claims };
+}
+
+// Check the request's credentials according to AUTH_MODE; successful JWT
+// checks carry the decoded claims for handlers to read
+export function authenticate(req: Request, url: URL, pathname: string): AuthResult {
+  if (req.method === 'OPTIONS' || PUBLIC_PATHS.has(pathname)) {
+    return { ok: true };
+  }
+  
+  return config.AUTH_MODE === 'jwt'
+    ? authenticateJwt(req, url, pathname)
+    : authenticateApiKey(req, url, pathname);
+}
@@ -0,0 +1,48 @@
+import { describe, test, expect } from 'bun:test';
+import { config } from './config';
+import { formatBody } from './bodyLog';
+import { restoreConfigAfterEach } from './testHelpers';
+
+restoreConfigAfterEach();
+
+describe('formatBody', () => {
+  test('redacts sensitive fields at any depth, whatever their case', () => {
+    const body = JSON.stringify({
+      Authorization: 'Bearer secret',
+      nested: { api_key: 'key-1', items: [{ PASSWORD: 'hunter2', keep: 1 }] },
+      tool_name: 'Bash'
+    });
+    
+    expect(JSON.parse(formatBody(body))).toEqual({
+      Authorization: '[REDACTED]',
+      nested: { api_key: '[REDACTED]', items: [{ PASSWORD: '[REDACTED]', keep: 1 }] },
+      tool_name: 'Bash'
+    });
+  });
+  
+  test('passes non-JSON bodies through unchanged', () => {
+    expect(formatBody('plain text body')).toBe('plain text body');
+  });
+  
+  test('truncates bodies over DEBUG_BODY_LOG_MAX_BYTES and reports the full size', () => {
+    config.DEBUG_BODY_LOG_MAX_BYTES = 10;
+    
+    expect(formatBody('abcdefghijklmnopqrst')).toBe('abcdefghij… [truncated, 20 bytes]');
+  });
+  
+  test('leaves bodies at the cap untouched', () => {
+    config.DEBUG_BODY_LOG_MAX_BYTES = 10;
+    
+    expect(formatBody('abcdefghij')).toBe('abcdefghij');
+  });
+  
+  test('redacts before truncating, so a secret never survives in the kept prefix', () => {
+    config.DEBUG_BODY_LOG_MAX_BYTES = 30;
+    
+    const formatted = formatBody(JSON.stringify({ password: 'a-rather-long-secret-value', padding: 'x'.repeat(50) }));
+    
+    expect(formatted).toStartWith('{"password":"[REDACTED]"');
+    expect(formatted).not.toContain('a-rather-long-secret-value');
+    expect(formatted).toContain('[truncated,');
+  });
+});
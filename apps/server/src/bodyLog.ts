@@ -0,0 +1,61 @@
+import { config } from './config';
+import { createLogger } from './logger';
+
+const logger = createLogger('body');
+
+// Fields whose values never reach the logs, matched case-insensitively at any depth
+const REDACTED_FIELDS = new Set(['authorization', 'api_key', 'password']);
+
+function redact(value: unknown): unknown {
+  if (Array.isArray(value)) {
+    return value.map(redact);
+  }
+  if (value && typeof value === 'object') {
+    return Object.fromEntries(Object.entries(value).map(([key, field]) =>
+      [key, REDACTED_FIELDS.has(key.toLowerCase()) ? '[REDACTED]' : redact(field)]
+    ));
+  }
+  return value;
+}
+
+// Redact JSON bodies, then cap the logged text at DEBUG_BODY_LOG_MAX_BYTES
+export function formatBody(text: string): string {
+  let formatted = text;
+  try {
+    formatted = JSON.stringify(redact(JSON.parse(text)));
+  } catch (error) {
+    // Not JSON: logged as-is, there are no fields to redact
+  }
+  
+  const bytes = Buffer.from(formatted, 'utf8');
+  if (bytes.byteLength <= config.DEBUG_BODY_LOG_MAX_BYTES) {
+    return formatted;
+  }
+  return `${bytes.subarray(0, config.DEBUG_BODY_LOG_MAX_BYTES).toString('utf8')}… [truncated, ${bytes.byteLength} bytes]`;
+}
+
+// Compressed bodies would only log as binary noise
+function isLoggable(body: ReadableStream<Uint8Array> | null, headers: Headers): boolean {
+  return body !== null && !headers.has('content-encoding');
+}
+
+// Log the request and response bodies at debug level when DEBUG_BODY_LOG is on. Works on
+// clones, so the handler and the client still get the original bodies; callers must not
+// pass streaming responses, which would be buffered in full. After a timeout the request
+// body may never finish arriving, so pass `timedOut` to leave it out instead of waiting.
+export async function logBodies(req: Request, res: Response | undefined, requestId: string, timedOut: boolean = false): Promise<void> {
+  if (!config.DEBUG_BODY_LOG) return;
+  
+  const fields: Record<string, unknown> = { request_id: requestId, method: req.method, path: new URL(req.url).pathname };
+  if (timedOut) {
+    fields.request_body = '[not logged: request timed out]';
+    void req.body?.cancel();
+  } else if (isLoggable(req.body, req.headers)) {
+    fields.request_body = formatBody(await req.text());
+  }
+  if (res && isLoggable(res.body, res.headers)) {
+    fields.status = res.status;
+    fields.response_body = formatBody(await res.clone().text());
+  }
+  logger.debug('request bodies', fields);
+}
@@ -0,0 +1,37 @@
+import { describe, test, expect } from 'bun:test';
+import { configSchema } from './config';
+
+function parsePort(value: string | undefined) {
+  return configSchema.shape.PORT.safeParse(value);
+}
+
+describe('PORT', () => {
+  test('defaults to 4000 when unset', () => {
+    expect(parsePort(undefined)).toMatchObject({ success: true, data: 4000 });
+  });
+  
+  test('accepts integers in range, ignoring surrounding whitespace', () => {
+    expect(parsePort('8080')).toMatchObject({ success: true, data: 8080 });
+    expect(parsePort(' 1 ')).toMatchObject({ success: true, data: 1 });
+    expect(parsePort('65535')).toMatchObject({ success: true, data: 65535 });
+  });
+  
+  test.each(['', 'abc', '"4000"', '4000.5', '-1', '0', '65536', '80 80'])('rejects %p and names the value', (value) => {
+    const result = parsePort(value);
+    
+    expect(result.success).toBe(false);
+    expect(result.error?.issues[0]?.message).toBe(`invalid PORT value '${value}', expected an integer between 1 and 65535`);
+  });
+});
+
+describe('LOG_LEVEL', () => {
+  test('trace is accepted and logs at debug', () => {
+    expect(configSchema.shape.LOG_LEVEL.safeParse('trace')).toMatchObject({ success: true, data: 'debug' });
+  });
+  
+  test('other levels pass through and unknown ones are rejected', () => {
+    expect(configSchema.shape.LOG_LEVEL.safeParse('warn')).toMatchObject({ success: true, data: 'warn' });
+    expect(configSchema.shape.LOG_LEVEL.safeParse(undefined)).toMatchObject({ success: true, data: 'info' });
+    expect(configSchema.shape.LOG_LEVEL.safeParse('verbose').success).toBe(false);
+  });
+});
@@ -1,18 +1,106 @@
 import { z } from 'zod';
+import { isIP } from 'node:net';
+import { createLogger } from './logger';
+
+const DEFAULT_HOST = '127.0.0.1';
+
+// Boolean switches: 'true', '1' and 'yes' turn them on (z.coerce.boolean would treat 'false' as true)
+const envFlag = (defaultValue: boolean) => z
+  .string()
+  .default(String(defaultValue))
+  .transform((val) => ['true', '1', 'yes'].includes(val.trim().toLowerCase()));
 
 // Define the configuration schema
-const configSchema = z.object({
+export const configSchema = z.object({
   // Server configuration
-  PORT: z.coerce.number().min(1).max(65535).default(4000),
+  // Parsed by hand so an empty or quoted PORT reports the offending value instead of a generic type error
+  PORT: z
+    .string()
+    .default('4000')
+    .transform((val, ctx) => {
+      const trimmed = val.trim();
+      const port = Number(trimmed);
+      if (!/^\d+$/.test(trimmed) || port < 1 || port > 65535) {
+        ctx.addIssue({ code: 'custom', message: `invalid PORT value '${val}', expected an integer between 1 and 65535` });
+        return z.NEVER;
+      }
+      return port;
+    }),
+  HOST: z
+    .string()
+    .default(DEFAULT_HOST)
+    .transform((val) => {
+      if (isIP(val.trim())) return val.trim();
+      console.warn(`⚠️  Invalid HOST value '${val}', falling back to ${DEFAULT_HOST}`);
+      return DEFAULT_HOST;
+    }),
+  
+  // Serve every route under this prefix (e.g. /observability) when mounted at a subpath; a trailing slash is dropped
+  BASE_PATH: z
+    .string()
+    .default('')
+    .transform((val, ctx) => {
+      const trimmed = val.trim().replace(/\/+$/, '');
+      if (trimmed && !/^\/[A-Za-z0-9._~\/-]*$/.test(trimmed)) {
+        ctx.addIssue({ code: 'custom', message: `invalid BASE_PATH value '${val}', expected a path starting with '/'` });
+        return z.NEVER;
+      }
+      return trimmed;
+    }),
+  
+  // Behind a reverse proxy, take the client IP from X-Forwarded-For / X-Real-IP instead of the socket
+  TRUST_PROXY: envFlag(false),
+  
+  // Seconds an idle (keep-alive) connection stays open; Bun caps this at 255
+  IDLE_TIMEOUT_SECONDS: z.coerce.number().int().min(0).max(255).default(10),
+  
+  // Abort requests whose handler (including body parsing) takes longer than this
+  REQUEST_TIMEOUT_MS: z.coerce.number().int().min(1).default(30000),
+  
+  // Optional: most requests handled at once; further requests get 503 (unlimited when unset)
+  MAX_CONNECTIONS: z.coerce.number().int().positive().optional(),
+  
+  // Largest accepted request body; Stop events carry the full chat transcript, so keep this generous
+  MAX_BODY_BYTES: z.coerce.number().int().min(1).default(10 * 1024 * 1024),
+  
+  // Trim and lowercase source_app on ingestion so agent names group consistently
+  NORMALIZE_SOURCE_APP: envFlag(false),
+  
+  // Reject events whose hook_event_type isn't in KNOWN_EVENT_TYPES with 422
+  STRICT_EVENTS: envFlag(false),
+  KNOWN_EVENT_TYPES: z
+    .string()
+    .default('PreToolUse,PostToolUse,Notification,Stop,SubagentStop,PreCompact,UserPromptSubmit,SessionStart,SessionEnd')
+    .transform((val) => val.split(',').map(s => s.trim()).filter(Boolean)),
+  
+  // Largest serialized payload accepted for a single event within POST /events/batch
+  MAX_EVENT_PAYLOAD_BYTES: z.coerce.number().int().min(1).default(1024 * 1024),
+  
+  // How long, and how many, Idempotency-Key values on POST /events are remembered
+  IDEMPOTENCY_TTL_SECONDS: z.coerce.number().int().min(1).default(86400),
+  IDEMPOTENCY_MAX_KEYS: z.coerce.number().int().min(1).default(10000),
   
   // Database configuration
   DATABASE_PATH: z.string().min(1).default('events.db'),
   
+  // Maximum number of events kept; the oldest are evicted beyond this (0 = unbounded)
+  MAX_EVENTS: z.coerce.number().int().min(0).default(10000),
+  
+  // Optional: also evict events older than this many seconds, checked periodically
+  EVENT_TTL_SECONDS: z.coerce.number().int().positive().optional(),
+  
+  // Optional: also append every ingested event to this file as NDJSON, as a durable archive
+  EVENT_ARCHIVE_FILE: z.string().min(1).optional(),
+  
   // CORS configuration
   CORS_ORIGINS: z
     .string()
     .default('*')
     .transform((val) => val === '*' ? ['*'] : val.split(',').map(s => s.trim())),
+  // Send Access-Control-Allow-Credentials (requires an explicit CORS_ORIGINS list)
+  CORS_ALLOW_CREDENTIALS: envFlag(false),
+  // How long browsers may cache a preflight response (Access-Control-Max-Age)
+  CORS_MAX_AGE_SECONDS: z.coerce.number().int().min(0).optional(),
   
   // Optional: Future PostgreSQL support
   POSTGRES_URL: z.string().optional(),
@@ -26,18 +114,49 @@ const configSchema = z.object({
   DB_USER: z.string().optional(),
   
   // Optional: Authentication/API keys
+  // AUTH_MODE picks one scheme: X-Api-Key checked against API_KEY, or HS256 bearer JWTs signed with JWT_SECRET
+  AUTH_MODE: z.enum(['api_key', 'jwt']).default('api_key'),
   API_KEY: z.string().optional(),
   JWT_SECRET: z.string().optional(),
   
-  // Optional: Rate limiting
-  RATE_LIMIT_WINDOW_MS: z.coerce.number().default(900000), // 15 minutes
-  RATE_LIMIT_MAX_REQUESTS: z.coerce.number().default(100),
+  // Optional: Rate limiting for event ingestion (token bucket per client IP; disabled when RPS is unset)
+  RATE_LIMIT_RPS: z.coerce.number().positive().optional(),
+  RATE_LIMIT_BURST: z.coerce.number().int().positive().optional(),
+  // Up to this many random extra seconds are added to Retry-After so throttled clients don't retry in lockstep
+  RATE_LIMIT_JITTER_SECONDS: z.coerce.number().int().min(0).default(1),
+  
+  // Optional: admin endpoints (POST /admin/shutdown); only served when authentication is also enabled
+  ENABLE_ADMIN: envFlag(false),
+  
+  // Optional: GET /debug/state, a JSON snapshot of internal counters for diagnosing problems
+  ENABLE_DEBUG: envFlag(false),
+  
+  // Optional: POST every ingested event to this URL as well, retrying 5xx responses up to the given count
+  FORWARD_WEBHOOK_URL: z.string().url().optional(),
+  FORWARD_WEBHOOK_MAX_RETRIES: z.coerce.number().int().min(0).default(3),
+  // Each attempt is abandoned after this long, so a webhook that never answers can't pile up requests
+  FORWARD_WEBHOOK_TIMEOUT_MS: z.coerce.number().int().positive().default(5000),
+  // Events being forwarded at once, retries included; further events are dropped and logged
+  FORWARD_WEBHOOK_MAX_PENDING: z.coerce.number().int().positive().default(100),
   
   // Optional: WebSocket configuration
   WS_HEARTBEAT_INTERVAL: z.coerce.number().default(30000), // 30 seconds
   
-  // Optional: Logging level
-  LOG_LEVEL: z.enum(['error', 'warn', 'info', 'debug']).default('info'),
+  // Optional: Logging level; 'trace' is accepted for tools that set it and logs the same as 'debug'
+  LOG_LEVEL: z
+    .enum(['error', 'warn', 'info', 'debug', 'trace'])
+    .default('info')
+    .transform((level) => level === 'trace' ? 'debug' as const : level),
+  
+  // Optional: Log output format - human-readable text or one JSON object per line
+  LOG_FORMAT: z.enum(['text', 'json']).default('text'),
+  
+  // Optional: one structured log line per completed request (written at info level)
+  ACCESS_LOG: envFlag(false),
+  
+  // Optional: log request/response bodies at debug level, redacted and capped (troubleshooting only)
+  DEBUG_BODY_LOG: envFlag(false),
+  DEBUG_BODY_LOG_MAX_BYTES: z.coerce.number().int().min(1).default(4096),
   
   // Environment
   NODE_ENV: z.enum(['development', 'production', 'test']).default('development')
@@ -51,8 +170,26 @@ function loadConfig(): Config {
   try {
     const config = configSchema.parse({
       PORT: process.env.PORT,
+      HOST: process.env.HOST,
+      BASE_PATH: process.env.BASE_PATH,
+      TRUST_PROXY: process.env.TRUST_PROXY,
+      IDLE_TIMEOUT_SECONDS: process.env.IDLE_TIMEOUT_SECONDS,
+      REQUEST_TIMEOUT_MS: process.env.REQUEST_TIMEOUT_MS,
+      MAX_CONNECTIONS: process.env.MAX_CONNECTIONS,
+      MAX_BODY_BYTES: process.env.MAX_BODY_BYTES,
+      NORMALIZE_SOURCE_APP: process.env.NORMALIZE_SOURCE_APP,
+      STRICT_EVENTS: process.env.STRICT_EVENTS,
+      KNOWN_EVENT_TYPES: process.env.KNOWN_EVENT_TYPES,
+      MAX_EVENT_PAYLOAD_BYTES: process.env.MAX_EVENT_PAYLOAD_BYTES,
+      IDEMPOTENCY_TTL_SECONDS: process.env.IDEMPOTENCY_TTL_SECONDS,
+      IDEMPOTENCY_MAX_KEYS: process.env.IDEMPOTENCY_MAX_KEYS,
       DATABASE_PATH: process.env.DATABASE_PATH,
+      MAX_EVENTS: process.env.MAX_EVENTS,
+      EVENT_TTL_SECONDS: process.env.EVENT_TTL_SECONDS,
+      EVENT_ARCHIVE_FILE: process.env.EVENT_ARCHIVE_FILE,
       CORS_ORIGINS: process.env.CORS_ORIGINS,
+      CORS_ALLOW_CREDENTIALS: process.env.CORS_ALLOW_CREDENTIALS,
+      CORS_MAX_AGE_SECONDS: process.env.CORS_MAX_AGE_SECONDS,
       POSTGRES_URL: process.env.POSTGRES_URL,
       DATABASE_URL: process.env.DATABASE_URL,
       DB_PASSWORD: process.env.DB_PASSWORD,
@@ -60,12 +197,24 @@ function loadConfig(): Config {
       DB_PORT: process.env.DB_PORT,
       DB_NAME: process.env.DB_NAME,
       DB_USER: process.env.DB_USER,
+      AUTH_MODE: process.env.AUTH_MODE,
       API_KEY: process.env.API_KEY,
       JWT_SECRET: process.env.JWT_SECRET,
-      RATE_LIMIT_WINDOW_MS: process.env.RATE_LIMIT_WINDOW_MS,
-      RATE_LIMIT_MAX_REQUESTS: process.env.RATE_LIMIT_MAX_REQUESTS,
+      RATE_LIMIT_RPS: process.env.RATE_LIMIT_RPS,
+      RATE_LIMIT_BURST: process.env.RATE_LIMIT_BURST,
+      RATE_LIMIT_JITTER_SECONDS: process.env.RATE_LIMIT_JITTER_SECONDS,
+      ENABLE_ADMIN: process.env.ENABLE_ADMIN,
+      ENABLE_DEBUG: process.env.ENABLE_DEBUG,
+      FORWARD_WEBHOOK_URL: process.env.FORWARD_WEBHOOK_URL,
+      FORWARD_WEBHOOK_MAX_RETRIES: process.env.FORWARD_WEBHOOK_MAX_RETRIES,
+      FORWARD_WEBHOOK_TIMEOUT_MS: process.env.FORWARD_WEBHOOK_TIMEOUT_MS,
+      FORWARD_WEBHOOK_MAX_PENDING: process.env.FORWARD_WEBHOOK_MAX_PENDING,
       WS_HEARTBEAT_INTERVAL: process.env.WS_HEARTBEAT_INTERVAL,
       LOG_LEVEL: process.env.LOG_LEVEL,
+      LOG_FORMAT: process.env.LOG_FORMAT,
+      ACCESS_LOG: process.env.ACCESS_LOG,
+      DEBUG_BODY_LOG: process.env.DEBUG_BODY_LOG,
+      DEBUG_BODY_LOG_MAX_BYTES: process.env.DEBUG_BODY_LOG_MAX_BYTES,
       NODE_ENV: process.env.NODE_ENV
     });
     
@@ -88,6 +237,10 @@ function loadConfig(): Config {
 // Export the validated configuration
 export const config = loadConfig();
 
+// Problems loading the config above go straight to the console, since the logger needs the
+// loaded LOG_LEVEL and LOG_FORMAT; everything reported from here on goes through it
+const logger = createLogger('config');
+
 // Helper function to validate required environment variables on startup
 export function validateRequiredConfig(): void {
   const requiredInProduction = ['DATABASE_PATH'];
@@ -95,15 +248,44 @@ export function validateRequiredConfig(): void {
   if (config.NODE_ENV === 'production') {
     for (const key of requiredInProduction) {
       if (!process.env[key]) {
-        console.error(`❌ Missing required environment variable: ${key}`);
+        logger.error(`❌ Missing required environment variable: ${key}`);
         process.exit(1);
       }
     }
   }
   
-  console.log('✅ Configuration loaded successfully');
-  console.log(`📦 Environment: ${config.NODE_ENV}`);
-  console.log(`🚀 Server will run on port: ${config.PORT}`);
-  console.log(`💾 Database path: ${config.DATABASE_PATH}`);
-  console.log(`🌐 CORS origins: ${Array.isArray(config.CORS_ORIGINS) ? config.CORS_ORIGINS.join(', ') : config.CORS_ORIGINS}`);
+  logger.info('✅ Configuration loaded successfully');
+  logger.info(`📦 Environment: ${config.NODE_ENV}`);
+  logger.info(`🚀 Server will bind to: ${config.HOST}:${config.PORT}`);
+  logger.info(`💾 Database path: ${config.DATABASE_PATH}`);
+  logger.info(`🗃️  Event retention: ${config.MAX_EVENTS > 0 ? `${config.MAX_EVENTS} most recent` : 'unbounded'}${config.EVENT_TTL_SECONDS ? `, at most ${config.EVENT_TTL_SECONDS}s old` : ''}`);
+  logger.info(`🔐 Auth mode: ${config.AUTH_MODE}`);
+  logger.info(`🌐 CORS origins: ${Array.isArray(config.CORS_ORIGINS) ? config.CORS_ORIGINS.join(', ') : config.CORS_ORIGINS}`);
+  
+  if (config.EVENT_ARCHIVE_FILE) {
+    logger.info(`📼 Archiving events to: ${config.EVENT_ARCHIVE_FILE}`);
+  }
+  
+  if (config.FORWARD_WEBHOOK_URL) {
+    logger.info(`📤 Forwarding events to: ${new URL(config.FORWARD_WEBHOOK_URL).origin}`);
+  }
+  
+  if (config.AUTH_MODE === 'jwt' && !config.JWT_SECRET) {
+    logger.error('❌ AUTH_MODE=jwt requires JWT_SECRET to be set');
+    process.exit(1);
+  }
+  
+  if (config.AUTH_MODE === 'api_key' && !config.API_KEY) {
+    logger.warn('⚠️  API_KEY is not set: authentication is disabled (development mode).');
+  }
+  
+  // The CORS spec forbids credentialed requests against a wildcard origin
+  if (config.CORS_ALLOW_CREDENTIALS && config.CORS_ORIGINS.includes('*')) {
+    logger.error('❌ CORS_ALLOW_CREDENTIALS=true requires an explicit CORS_ORIGINS list, not "*"');
+    process.exit(1);
+  }
+  
+  if (config.CORS_ORIGINS.includes('*')) {
+    logger.warn('⚠️  CORS_ORIGINS is "*": any origin may call this server. Set an explicit list outside local development.');
+  }
 }
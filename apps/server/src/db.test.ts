@@ -0,0 +1,47 @@
+import { describe, test, expect, beforeEach } from 'bun:test';
+import { initDatabase, insertEvent, iterateEvents, evictExpiredEvents } from './db';
+import { hookEvent } from './testHelpers';
+
+beforeEach(() => {
+  initDatabase();
+});
+
+describe('iterateEvents', () => {
+  test('walks every event oldest first', () => {
+    const ids = [1, 2, 3].map(() => insertEvent(hookEvent()).id);
+    
+    expect([...iterateEvents()].map(event => event.id)).toEqual(ids);
+  });
+  
+  test('after_id yields only the events stored after that id, in order', () => {
+    const ids = [1, 2, 3, 4].map(() => insertEvent(hookEvent()).id!);
+    
+    expect([...iterateEvents({ after_id: ids[1] })].map(event => event.id)).toEqual(ids.slice(2));
+  });
+  
+  test('after_id of the newest event yields nothing', () => {
+    insertEvent(hookEvent());
+    const newest = insertEvent(hookEvent());
+    
+    expect([...iterateEvents({ after_id: newest.id })]).toEqual([]);
+  });
+  
+  test('after_id combines with session_id', () => {
+    const first = insertEvent(hookEvent({ session_id: 'a' }));
+    insertEvent(hookEvent({ session_id: 'b' }));
+    const third = insertEvent(hookEvent({ session_id: 'a' }));
+    
+    expect([...iterateEvents({ session_id: 'a', after_id: first.id })].map(event => event.id)).toEqual([third.id]);
+  });
+});
+
+describe('evictExpiredEvents', () => {
+  test('drops events older than the TTL and keeps fresh ones', () => {
+    const now = Date.now();
+    insertEvent(hookEvent({ timestamp: now - 10000 }));
+    const fresh = insertEvent(hookEvent({ timestamp: now - 1000 }));
+    
+    expect(evictExpiredEvents(5000, now)).toBe(1);
+    expect([...iterateEvents()].map(event => event.id)).toEqual([fresh.id]);
+  });
+});
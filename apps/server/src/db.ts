@@ -1,9 +1,18 @@
 import { Database } from 'bun:sqlite';
-import type { HookEvent, FilterOptions, Theme, ThemeSearchQuery } from './types';
+import type { HookEvent, EventAnnotation, EventFilter, EventStats, SessionSummary, FilterOptions, Theme, ThemeSearchQuery } from './types';
 import { config } from './config';
 
 let db: Database;
 
+// Columns read back into a HookEvent by rowToEvent
+const EVENT_COLUMNS = 'id, source_app, session_id, hook_event_type, payload, chat, summary, timestamp, tags, note';
+let ready = false;
+let evictedEvents = 0;
+// Bumped by every annotation, so exports can tell when a stored event's bytes changed.
+// The count restarts with the process, so exportVersion() pairs it with a per-process id.
+let annotationRevision = 0;
+const processId = crypto.randomUUID().slice(0, 8);
+
 export function initDatabase(): void {
   db = new Database(config.DATABASE_PATH);
   
@@ -21,7 +30,9 @@ export function initDatabase(): void {
       payload TEXT NOT NULL,
       chat TEXT,
       summary TEXT,
-      timestamp INTEGER NOT NULL
+      timestamp INTEGER NOT NULL,
+      tags TEXT,
+      note TEXT
     )
   `);
   
@@ -38,6 +49,14 @@ export function initDatabase(): void {
     if (!hasSummaryColumn) {
       db.exec('ALTER TABLE events ADD COLUMN summary TEXT');
     }
+    
+    // Annotation columns written by PATCH /events/:id
+    if (!columns.some((col: any) => col.name === 'tags')) {
+      db.exec('ALTER TABLE events ADD COLUMN tags TEXT');
+    }
+    if (!columns.some((col: any) => col.name === 'note')) {
+      db.exec('ALTER TABLE events ADD COLUMN note TEXT');
+    }
   } catch (error) {
     // If the table doesn't exist yet, the CREATE TABLE above will handle it
   }
@@ -103,6 +122,31 @@ export function initDatabase(): void {
   db.exec('CREATE INDEX IF NOT EXISTS idx_themes_createdAt ON themes(createdAt)');
   db.exec('CREATE INDEX IF NOT EXISTS idx_theme_shares_token ON theme_shares(shareToken)');
   db.exec('CREATE INDEX IF NOT EXISTS idx_theme_ratings_theme ON theme_ratings(themeId)');
+  
+  ready = true;
+}
+
+// Round-trip a trivial query to prove the database is actually usable
+export function pingDatabase(): { ok: true } | { ok: false; error: string } {
+  if (!ready) {
+    return { ok: false, error: 'database not initialized' };
+  }
+  
+  try {
+    db.prepare('SELECT 1').get();
+    return { ok: true };
+  } catch (error) {
+    return { ok: false, error: error instanceof Error ? error.message : String(error) };
+  }
+}
+
+export function closeDatabase(): void {
+  ready = false;
+  if (db) {
+    // Fold the WAL back into the main database file so the last events are in events.db itself
+    db.exec('PRAGMA wal_checkpoint(TRUNCATE)');
+    db.close();
+  }
 }
 
 export function insertEvent(event: HookEvent): HookEvent {
@@ -111,21 +155,91 @@ export function insertEvent(event: HookEvent): HookEvent {
     VALUES (?, ?, ?, ?, ?, ?, ?)
   `);
   
-  const timestamp = event.timestamp || Date.now();
-  const result = stmt.run(
-    event.source_app,
-    event.session_id,
-    event.hook_event_type,
-    JSON.stringify(event.payload),
-    event.chat ? JSON.stringify(event.chat) : null,
-    event.summary || null,
-    timestamp
-  );
+  const row = {
+    source_app: event.source_app,
+    session_id: event.session_id,
+    hook_event_type: event.hook_event_type,
+    payload: JSON.stringify(event.payload),
+    chat: event.chat ? JSON.stringify(event.chat) : null,
+    summary: event.summary || null,
+    timestamp: event.timestamp || Date.now()
+  };
+  const result = stmt.run(row.source_app, row.session_id, row.hook_event_type, row.payload, row.chat, row.summary, row.timestamp);
+  
+  const id = result.lastInsertRowid as number;
+  evictOldestEvents(id);
+  
+  // Built from the stored columns, so keys the client sent that weren't persisted (tags,
+  // note, anything unknown) don't show up in the response or broadcast and then vanish on reload
+  return rowToEvent({ ...row, id, tags: null, note: null });
+}
+
+// Insert a batch atomically: either every event is stored or none are
+export function insertEvents(events: HookEvent[]): HookEvent[] {
+  return db.transaction((batch: HookEvent[]) => batch.map(insertEvent))(events);
+}
+
+// Keep the events table bounded: ids are assigned in insertion order, so
+// everything at or below (newest id - MAX_EVENTS) is the overflow
+function evictOldestEvents(newestId: number): void {
+  if (config.MAX_EVENTS <= 0) return;
   
+  const cutoff = newestId - config.MAX_EVENTS;
+  if (cutoff <= 0) return;
+  
+  const result = db.prepare('DELETE FROM events WHERE id <= ?').run(cutoff);
+  evictedEvents += result.changes;
+}
+
+export function getEventById(id: number): HookEvent | undefined {
+  const row = db.prepare(`
+    SELECT ${EVENT_COLUMNS}
+    FROM events
+    WHERE id = ?
+  `).get(id) as any;
+  
+  return row ? rowToEvent(row) : undefined;
+}
+
+// Merge annotations into a stored event; fields left undefined keep their current value
+// and a null note clears it. The payload and timestamp are never touched.
+export function annotateEvent(id: number, annotation: EventAnnotation): HookEvent | undefined {
+  const sets: string[] = [];
+  const params: any[] = [];
+  
+  if (annotation.tags !== undefined) {
+    sets.push('tags = ?');
+    params.push(JSON.stringify(annotation.tags));
+  }
+  
+  if (annotation.note !== undefined) {
+    sets.push('note = ?');
+    params.push(annotation.note);
+  }
+  
+  if (sets.length > 0) {
+    const result = db.prepare(`UPDATE events SET ${sets.join(', ')} WHERE id = ?`).run(...params, id);
+    if (result.changes > 0) {
+      annotationRevision++;
+    }
+  }
+  
+  return getEventById(id);
+}
+
+// Time-based retention: drop events whose timestamp is older than maxAgeMs
+export function evictExpiredEvents(maxAgeMs: number, now: number = Date.now()): number {
+  const result = db.prepare('DELETE FROM events WHERE timestamp < ?').run(now - maxAgeMs);
+  evictedEvents += result.changes;
+  return result.changes;
+}
+
+export function getEventCount(): { count: number; capacity: number; evicted: number } {
+  const row = db.prepare('SELECT COUNT(*) AS count FROM events').get() as { count: number };
   return {
-    ...event,
-    id: result.lastInsertRowid as number,
-    timestamp
+    count: row.count,
+    capacity: config.MAX_EVENTS,
+    evicted: evictedEvents
   };
 }
 
@@ -143,7 +257,7 @@ export function getFilterOptions(): FilterOptions {
 
 export function getRecentEvents(limit: number = 100): HookEvent[] {
   const stmt = db.prepare(`
-    SELECT id, source_app, session_id, hook_event_type, payload, chat, summary, timestamp
+    SELECT ${EVENT_COLUMNS}
     FROM events
     ORDER BY timestamp DESC
     LIMIT ?
@@ -151,7 +265,128 @@ export function getRecentEvents(limit: number = 100): HookEvent[] {
   
   const rows = stmt.all(limit) as any[];
   
-  return rows.map(row => ({
+  return rows.map(rowToEvent).reverse();
+}
+
+export function getEvents(filter: EventFilter = {}): HookEvent[] {
+  let sql = `SELECT ${EVENT_COLUMNS} FROM events WHERE 1=1`;
+  const params: any[] = [];
+  
+  if (filter.source_app) {
+    sql += ' AND source_app = ?';
+    params.push(filter.source_app);
+  }
+  
+  if (filter.session_id) {
+    sql += ' AND session_id = ?';
+    params.push(filter.session_id);
+  }
+  
+  if (filter.hook_event_type) {
+    sql += ' AND hook_event_type = ?';
+    params.push(filter.hook_event_type);
+  }
+  
+  if (filter.search) {
+    // Escape LIKE wildcards so the search term is matched literally; LIKE is case-insensitive for ASCII
+    const pattern = `%${filter.search.replace(/[\\%_]/g, '\\$&')}%`;
+    sql += " AND (payload LIKE ? ESCAPE '\\' OR hook_event_type LIKE ? ESCAPE '\\')";
+    params.push(pattern, pattern);
+  }
+  
+  if (filter.since !== undefined) {
+    sql += ' AND timestamp >= ?';
+    params.push(filter.since);
+  }
+  
+  if (filter.until !== undefined) {
+    sql += ' AND timestamp < ?';
+    params.push(filter.until);
+  }
+  
+  // Newest first
+  sql += ' ORDER BY timestamp DESC, id DESC LIMIT ?';
+  params.push(filter.limit ?? 100);
+  
+  const rows = db.prepare(sql).all(...params) as any[];
+  return rows.map(rowToEvent);
+}
+
+// Walk every matching event oldest first without loading the whole table into memory;
+// after_id restricts it to events stored after that id
+export function* iterateEvents(filter: Pick<EventFilter, 'session_id'> & { after_id?: number } = {}): Generator<HookEvent> {
+  let sql = `SELECT ${EVENT_COLUMNS} FROM events WHERE 1=1`;
+  const params: any[] = [];
+  
+  if (filter.session_id) {
+    sql += ' AND session_id = ?';
+    params.push(filter.session_id);
+  }
+  
+  if (filter.after_id !== undefined) {
+    sql += ' AND id > ?';
+    params.push(filter.after_id);
+  }
+  
+  sql += ' ORDER BY id ASC';
+  
+  for (const row of db.prepare(sql).iterate(...params)) {
+    yield rowToEvent(row);
+  }
+}
+
+// Identifies what iterateEvents would return for this filter: eviction changes the first id or
+// the count, new events the last id, and annotations the revision. Used to build export ETags.
+export function exportVersion(filter: Pick<EventFilter, 'session_id'> = {}): string {
+  let sql = 'SELECT MIN(id) AS first_id, MAX(id) AS last_id, COUNT(*) AS count FROM events';
+  const params: any[] = [];
+  
+  if (filter.session_id) {
+    sql += ' WHERE session_id = ?';
+    params.push(filter.session_id);
+  }
+  
+  const row = db.prepare(sql).get(...params) as { first_id: number | null; last_id: number | null; count: number };
+  return `${row.first_id ?? 0}-${row.last_id ?? 0}-${row.count}-${processId}.${annotationRevision}`;
+}
+
+// One row per (session, source app) pair, most recently active first
+export function getSessions(limit: number = 50): SessionSummary[] {
+  return db.prepare(`
+    SELECT session_id, source_app, COUNT(*) AS event_count, MIN(timestamp) AS first_seen, MAX(timestamp) AS last_seen
+    FROM events
+    GROUP BY session_id, source_app
+    ORDER BY last_seen DESC
+    LIMIT ?
+  `).all(limit) as SessionSummary[];
+}
+
+export function getSessionEvents(sessionId: string): HookEvent[] {
+  const rows = db.prepare(`
+    SELECT ${EVENT_COLUMNS}
+    FROM events
+    WHERE session_id = ?
+    ORDER BY timestamp ASC, id ASC
+  `).all(sessionId) as any[];
+  
+  return rows.map(rowToEvent);
+}
+
+export function getEventStats(): EventStats {
+  const totals = db.prepare('SELECT COUNT(*) AS total, MAX(timestamp) AS latest FROM events').get() as { total: number; latest: number | null };
+  const byType = db.prepare('SELECT hook_event_type AS key, COUNT(*) AS count FROM events GROUP BY hook_event_type').all() as { key: string; count: number }[];
+  const byApp = db.prepare('SELECT source_app AS key, COUNT(*) AS count FROM events GROUP BY source_app').all() as { key: string; count: number }[];
+  
+  return {
+    total: totals.total,
+    by_event_type: Object.fromEntries(byType.map(row => [row.key, row.count])),
+    by_source_app: Object.fromEntries(byApp.map(row => [row.key, row.count])),
+    latest_timestamp: totals.latest
+  };
+}
+
+function rowToEvent(row: any): HookEvent {
+  return {
     id: row.id,
     source_app: row.source_app,
     session_id: row.session_id,
@@ -159,8 +394,10 @@ export function getRecentEvents(limit: number = 100): HookEvent[] {
     payload: JSON.parse(row.payload),
     chat: row.chat ? JSON.parse(row.chat) : undefined,
     summary: row.summary || undefined,
-    timestamp: row.timestamp
-  })).reverse();
+    timestamp: row.timestamp,
+    tags: row.tags ? JSON.parse(row.tags) : undefined,
+    note: row.note ?? undefined
+  };
 }
 
 // Theme database functions
@@ -0,0 +1,26 @@
+// Swagger UI page for exploring the API, loaded from the swagger-ui-dist CDN bundle.
+// The spec URL is relative so the page also works under the /v1 prefix.
+// The bundle is pinned to an exact release so the CDN can't change the code this page runs.
+const SWAGGER_UI_VERSION = '5.17.14';
+const SWAGGER_UI_CDN = `https://unpkg.com/swagger-ui-dist@${SWAGGER_UI_VERSION}`;
+
+export const SWAGGER_UI_HTML = `<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <meta name="viewport" content="width=device-width, initial-scale=1" />
+  <title>Multi-Agent Observability API</title>
+  <link rel="stylesheet" href="${SWAGGER_UI_CDN}/swagger-ui.css" crossorigin />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="${SWAGGER_UI_CDN}/swagger-ui-bundle.js" crossorigin></script>
+  <script>
+    window.ui = SwaggerUIBundle({
+      url: './openapi.json',
+      dom_id: '#swagger-ui'
+    });
+  </script>
+</body>
+</html>
+`;
@@ -0,0 +1,129 @@
+import { describe, test, expect, beforeEach, afterEach } from 'bun:test';
+import { mkdtempSync, readFileSync, rmSync } from 'node:fs';
+import { tmpdir } from 'node:os';
+import { join } from 'node:path';
+import { fileEventSink, teeEventSink } from './eventSink';
+import type { EventSink } from './eventSink';
+import type { HookEvent } from './types';
+import { hookEvent } from './testHelpers';
+
+// Keeps recorded events in an array, assigning ids the way the SQLite sink does
+function memorySink(): EventSink & { events: HookEvent[] } {
+  const events: HookEvent[] = [];
+  const store = (event: HookEvent): HookEvent => {
+    const stored = { ...event, id: event.id ?? events.length + 1, timestamp: event.timestamp ?? 1000 };
+    events.push(stored);
+    return stored;
+  };
+  
+  return {
+    events,
+    record: store,
+    recordBatch: (batch) => batch.map(store),
+    annotate: () => undefined,
+    query: () => events,
+    stats: () => ({ total: events.length, by_event_type: {}, by_source_app: {}, latest_timestamp: null })
+  };
+}
+
+function failingSink(): EventSink {
+  const fail = (): never => {
+    throw new Error('sink unavailable');
+  };
+  return { record: fail, recordBatch: fail, annotate: fail, query: fail, stats: fail };
+}
+
+describe('teeEventSink', () => {
+  test('every sink receives the copy stored by the primary', () => {
+    const primary = memorySink();
+    const secondary = memorySink();
+    const tee = teeEventSink([primary, secondary]);
+    
+    const stored = tee.record(hookEvent());
+    
+    expect(stored.id).toBe(1);
+    expect(primary.events).toEqual([stored]);
+    expect(secondary.events).toEqual([stored]);
+  });
+  
+  test('batches fan out to every sink', () => {
+    const primary = memorySink();
+    const secondary = memorySink();
+    const tee = teeEventSink([primary, secondary]);
+    
+    const stored = tee.recordBatch([hookEvent(), hookEvent()]);
+    
+    expect(stored).toHaveLength(2);
+    expect(secondary.events).toEqual(stored);
+  });
+  
+  test('a failing primary does not stop the other sinks from recording', () => {
+    const secondary = memorySink();
+    const tee = teeEventSink([failingSink(), secondary]);
+    
+    const stored = tee.record(hookEvent());
+    
+    expect(secondary.events).toEqual([stored]);
+  });
+  
+  test('a failing secondary does not fail the write', () => {
+    const primary = memorySink();
+    const tee = teeEventSink([primary, failingSink()]);
+    
+    expect(() => tee.record(hookEvent())).not.toThrow();
+    expect(primary.events).toHaveLength(1);
+  });
+  
+  test('the write fails when every sink fails', () => {
+    const tee = teeEventSink([failingSink(), failingSink()]);
+    
+    expect(() => tee.record(hookEvent())).toThrow('sink unavailable');
+  });
+  
+  test('reads go to the primary only', () => {
+    const primary = memorySink();
+    const tee = teeEventSink([primary, failingSink()]);
+    tee.record(hookEvent());
+    
+    expect(tee.query({})).toEqual(primary.events);
+  });
+});
+
+describe('fileEventSink', () => {
+  let dir: string;
+  
+  beforeEach(() => {
+    dir = mkdtempSync(join(tmpdir(), 'event-archive-'));
+  });
+  
+  afterEach(() => {
+    rmSync(dir, { recursive: true, force: true });
+  });
+  
+  test('appends each event as one NDJSON line', () => {
+    const path = join(dir, 'events.ndjson');
+    const sink = fileEventSink(path);
+    
+    sink.record(hookEvent({ id: 1 }));
+    sink.recordBatch([hookEvent({ id: 2 }), hookEvent({ id: 3 })]);
+    
+    const lines = readFileSync(path, 'utf8').trimEnd().split('\n');
+    expect(lines.map(line => (JSON.parse(line) as HookEvent).id)).toEqual([1, 2, 3]);
+  });
+  
+  test('is write-only', () => {
+    const sink = fileEventSink(join(dir, 'events.ndjson'));
+    
+    expect(() => sink.query({})).toThrow('write-only');
+    expect(() => sink.stats()).toThrow('write-only');
+  });
+  
+  test('archives what the primary stored when teed behind it', () => {
+    const path = join(dir, 'events.ndjson');
+    const tee = teeEventSink([memorySink(), fileEventSink(path)]);
+    
+    const stored = tee.record(hookEvent());
+    
+    expect(JSON.parse(readFileSync(path, 'utf8'))).toEqual(stored);
+  });
+});
@@ -0,0 +1,88 @@
+import { appendFileSync } from 'node:fs';
+import { insertEvent, insertEvents, annotateEvent, getEvents, getEventStats } from './db';
+import { createLogger } from './logger';
+import type { HookEvent, EventAnnotation, EventFilter, EventStats } from './types';
+
+const logger = createLogger('sink');
+
+// Where ingested events are stored and read back from. The HTTP handlers only talk
+// to this interface, so another backend can be swapped in without touching them.
+export interface EventSink {
+  // Store one event, returning it with its assigned id and timestamp
+  record(event: HookEvent): HookEvent;
+  // Store several events atomically: all of them or none
+  recordBatch(events: HookEvent[]): HookEvent[];
+  // Merge tags/note into a stored event; undefined when no event has this id
+  annotate(id: number, annotation: EventAnnotation): HookEvent | undefined;
+  query(filter: EventFilter): HookEvent[];
+  stats(): EventStats;
+}
+
+// The default sink: the SQLite events table managed by db.ts
+export const sqliteEventSink: EventSink = {
+  record: insertEvent,
+  recordBatch: insertEvents,
+  annotate: annotateEvent,
+  query: getEvents,
+  stats: getEventStats
+};
+
+// Append-only archive: each recorded event becomes one NDJSON line in `path`. It keeps
+// no index, so it can only be written; pair it with a queryable sink via teeEventSink.
+export function fileEventSink(path: string): EventSink {
+  const unsupported = (): never => {
+    throw new Error(`event archive ${path} is write-only`);
+  };
+  
+  return {
+    record(event) {
+      appendFileSync(path, JSON.stringify(event) + '\n');
+      return event;
+    },
+    recordBatch(events) {
+      if (events.length > 0) {
+        appendFileSync(path, events.map(event => JSON.stringify(event) + '\n').join(''));
+      }
+      return events;
+    },
+    annotate: unsupported,
+    query: unsupported,
+    stats: unsupported
+  };
+}
+
+// Fan each write out to every sink, e.g. SQLite for the dashboard plus a file archive.
+// The first sink is the primary: reads and annotations go only to it, and the copy it
+// stored (with id and timestamp) is what the others get. A write fails only when every
+// sink fails; failures of the rest are logged.
+export function teeEventSink(sinks: [EventSink, ...EventSink[]]): EventSink {
+  const [primary] = sinks;
+  
+  function fanOut<T>(write: (sink: EventSink, input: T) => T, input: T): T {
+    let stored: T | undefined;
+    let lastError: unknown;
+    
+    for (const [index, sink] of sinks.entries()) {
+      try {
+        const result = write(sink, stored ?? input);
+        stored ??= result;
+      } catch (error) {
+        lastError = error;
+        logger.error('Event sink failed to record', { sink: index, error });
+      }
+    }
+    
+    if (stored === undefined) {
+      throw lastError;
+    }
+    return stored;
+  }
+  
+  return {
+    record: (event) => fanOut((sink, input) => sink.record(input), event),
+    recordBatch: (events) => fanOut((sink, input) => sink.recordBatch(input), events),
+    annotate: (id, annotation) => primary.annotate(id, annotation),
+    query: (filter) => primary.query(filter),
+    stats: () => primary.stats()
+  };
+}
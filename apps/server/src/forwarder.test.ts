@@ -0,0 +1,45 @@
+import { describe, test, expect } from 'bun:test';
+import { config } from './config';
+import { forwardEvent } from './forwarder';
+import { hookEvent, mockWebhook, restoreConfigAfterEach, waitFor } from './testHelpers';
+
+restoreConfigAfterEach();
+
+describe('forwardEvent', () => {
+  test('an attempt the webhook never answers is abandoned after the timeout and retried', async () => {
+    const webhook = mockWebhook('hang');
+    config.FORWARD_WEBHOOK_URL = webhook.url;
+    config.FORWARD_WEBHOOK_TIMEOUT_MS = 50;
+    config.FORWARD_WEBHOOK_MAX_RETRIES = 1;
+    try {
+      forwardEvent({ ...hookEvent(), id: 1 });
+      
+      await waitFor(() => webhook.received.length === 2);
+    } finally {
+      webhook.stop();
+    }
+  });
+  
+  test('events beyond FORWARD_WEBHOOK_MAX_PENDING are dropped instead of queued', async () => {
+    const webhook = mockWebhook('hang');
+    config.FORWARD_WEBHOOK_URL = webhook.url;
+    config.FORWARD_WEBHOOK_TIMEOUT_MS = 200;
+    config.FORWARD_WEBHOOK_MAX_RETRIES = 0;
+    config.FORWARD_WEBHOOK_MAX_PENDING = 1;
+    try {
+      forwardEvent({ ...hookEvent(), id: 1 });
+      forwardEvent({ ...hookEvent(), id: 2 });
+      
+      await waitFor(() => webhook.received.length === 1);
+      await Bun.sleep(50);
+      expect(webhook.received).toEqual([{ ...hookEvent(), id: 1 }]);
+      
+      // Once the first attempt times out its slot is free again
+      await Bun.sleep(300);
+      forwardEvent({ ...hookEvent(), id: 3 });
+      await waitFor(() => webhook.received.length === 2);
+    } finally {
+      webhook.stop();
+    }
+  });
+});
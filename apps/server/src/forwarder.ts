@@ -0,0 +1,62 @@
+import { config } from './config';
+import { createLogger } from './logger';
+import type { LogFields } from './logger';
+import type { HookEvent } from './types';
+
+const logger = createLogger('forwarder');
+
+// Delay before the first retry; doubled for each further attempt
+const RETRY_BASE_DELAY_MS = 500;
+
+// Events currently being forwarded, bounded by FORWARD_WEBHOOK_MAX_PENDING
+let pendingForwards = 0;
+
+// Attempts are made until one succeeds, the webhook answers with a 4xx (which
+// would only fail again), or FORWARD_WEBHOOK_MAX_RETRIES retries are used up
+async function postEvent(url: string, event: HookEvent): Promise<void> {
+  const attempts = config.FORWARD_WEBHOOK_MAX_RETRIES + 1;
+  
+  for (let attempt = 1; attempt <= attempts; attempt++) {
+    let failure: LogFields;
+    try {
+      const res = await fetch(url, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(event),
+        signal: AbortSignal.timeout(config.FORWARD_WEBHOOK_TIMEOUT_MS)
+      });
+      if (res.ok) return;
+      
+      if (res.status < 500) {
+        logger.warn('Webhook rejected forwarded event', { event_id: event.id, status: res.status });
+        return;
+      }
+      failure = { status: res.status };
+    } catch (error) {
+      failure = { error };
+    }
+    
+    if (attempt === attempts) {
+      logger.warn('Giving up forwarding event to webhook', { event_id: event.id, attempts, ...failure });
+      return;
+    }
+    
+    await Bun.sleep(RETRY_BASE_DELAY_MS * 2 ** (attempt - 1));
+  }
+}
+
+// POST the event to FORWARD_WEBHOOK_URL in the background; ingestion never waits on it
+export function forwardEvent(event: HookEvent): void {
+  const url = config.FORWARD_WEBHOOK_URL;
+  if (!url) return;
+  
+  if (pendingForwards >= config.FORWARD_WEBHOOK_MAX_PENDING) {
+    logger.warn('Dropping forwarded event, too many pending', { event_id: event.id, pending: pendingForwards });
+    return;
+  }
+  
+  pendingForwards++;
+  void postEvent(url, event).finally(() => {
+    pendingForwards--;
+  });
+}
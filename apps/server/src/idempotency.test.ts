@@ -0,0 +1,31 @@
+import { describe, test, expect } from 'bun:test';
+import { config } from './config';
+import { lookupIdempotencyKey, rememberIdempotencyKey } from './idempotency';
+import { restoreConfigAfterEach } from './testHelpers';
+
+restoreConfigAfterEach();
+
+describe('idempotency keys', () => {
+  test('a remembered key maps to its event id until the TTL passes', () => {
+    config.IDEMPOTENCY_TTL_SECONDS = 60;
+    rememberIdempotencyKey('ttl-key', 7, 1000);
+    
+    expect(lookupIdempotencyKey('ttl-key', 1000 + 59999)).toBe(7);
+    expect(lookupIdempotencyKey('ttl-key', 1000 + 60000)).toBeUndefined();
+  });
+  
+  test('an unknown key is not found', () => {
+    expect(lookupIdempotencyKey('never-seen')).toBeUndefined();
+  });
+  
+  test('the oldest keys are forgotten beyond IDEMPOTENCY_MAX_KEYS', () => {
+    config.IDEMPOTENCY_MAX_KEYS = 2;
+    rememberIdempotencyKey('cap-a', 1, 0);
+    rememberIdempotencyKey('cap-b', 2, 0);
+    rememberIdempotencyKey('cap-c', 3, 0);
+    
+    expect(lookupIdempotencyKey('cap-a', 1)).toBeUndefined();
+    expect(lookupIdempotencyKey('cap-b', 1)).toBe(2);
+    expect(lookupIdempotencyKey('cap-c', 1)).toBe(3);
+  });
+});
@@ -0,0 +1,35 @@
+import { config } from './config';
+
+// Idempotency-Key header values seen on POST /events, mapped to the id of the
+// event they created. Map iteration order is insertion order, so the first
+// entry is always the oldest and is the one dropped when the map is full.
+interface IdempotencyEntry {
+  eventId: number;
+  expiresAt: number;
+}
+
+const entries = new Map<string, IdempotencyEntry>();
+
+// Event id previously stored under `key`, if it hasn't expired yet
+export function lookupIdempotencyKey(key: string, now: number = Date.now()): number | undefined {
+  const entry = entries.get(key);
+  if (!entry) return undefined;
+  
+  if (entry.expiresAt <= now) {
+    entries.delete(key);
+    return undefined;
+  }
+  
+  return entry.eventId;
+}
+
+export function rememberIdempotencyKey(key: string, eventId: number, now: number = Date.now()): void {
+  entries.delete(key);
+  entries.set(key, { eventId, expiresAt: now + config.IDEMPOTENCY_TTL_SECONDS * 1000 });
+  
+  while (entries.size > config.IDEMPOTENCY_MAX_KEYS) {
+    const oldest = entries.keys().next().value;
+    if (oldest === undefined) break;
+    entries.delete(oldest);
+  }
+}
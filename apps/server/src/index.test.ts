@@ -0,0 +1,1080 @@
+import { describe, test, expect, beforeEach, spyOn } from 'bun:test';
+import type { Server } from 'bun';
+import { handleFetch, setStartedAt } from './index';
+import { config } from './config';
+import { initDatabase, closeDatabase, getEventCount } from './db';
+import { renderMetrics } from './metrics';
+import type { HookEvent } from './types';
+import { hookEvent, mockWebhook, restoreConfigAfterEach, waitFor } from './testHelpers';
+
+// Stands in for Bun's server: handlers only ask it for the client address and to upgrade /stream
+const server = {
+  requestIP: () => ({ address: '203.0.113.7', family: 'IPv4', port: 51000 }),
+  upgrade: () => false,
+  timeout: () => {}
+} as unknown as Server;
+
+async function request(path: string, init?: RequestInit): Promise<Response> {
+  const res = await handleFetch(new Request(`http://localhost${path}`, init), server);
+  if (!res) {
+    throw new Error(`no response for ${path}`);
+  }
+  return res;
+}
+
+function postJson(path: string, body: unknown, headers: Record<string, string> = {}): Promise<Response> {
+  return request(path, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json', ...headers },
+    body: JSON.stringify(body)
+  });
+}
+
+// Run `send` with JSON logging at info level and return the access log entries it wrote
+async function accessLog(send: () => Promise<unknown>): Promise<Record<string, unknown>[]> {
+  config.LOG_LEVEL = 'info';
+  config.LOG_FORMAT = 'json';
+  const spy = spyOn(console, 'log').mockImplementation(() => {});
+  try {
+    await send();
+    return spy.mock.calls
+      .map(([line]) => JSON.parse(String(line)) as Record<string, unknown>)
+      .filter(entry => entry.target === 'access');
+  } finally {
+    spy.mockRestore();
+  }
+}
+
+restoreConfigAfterEach();
+
+beforeEach(() => {
+  initDatabase();
+});
+
+describe('POST /events with an Idempotency-Key', () => {
+  test('a retry with the same key returns the original event instead of storing a duplicate', async () => {
+    const first = await postJson('/events', hookEvent(), { 'Idempotency-Key': 'retry-1' });
+    const second = await postJson('/events', hookEvent(), { 'Idempotency-Key': 'retry-1' });
+    
+    expect(first.status).toBe(200);
+    expect(second.status).toBe(200);
+    expect(second.headers.get('Idempotent-Replayed')).toBe('true');
+    expect((await second.json()).id).toBe((await first.json()).id);
+    expect(getEventCount().count).toBe(1);
+  });
+  
+  test('different keys store separate events', async () => {
+    await postJson('/events', hookEvent(), { 'Idempotency-Key': 'retry-2' });
+    await postJson('/events', hookEvent(), { 'Idempotency-Key': 'retry-3' });
+    
+    expect(getEventCount().count).toBe(2);
+  });
+});
+
+describe('CORS', () => {
+  test('an allowlisted origin is echoed back', async () => {
+    config.CORS_ORIGINS = ['http://allowed.example'];
+    
+    const res = await request('/health', { headers: { Origin: 'http://allowed.example' } });
+    
+    expect(res.headers.get('Access-Control-Allow-Origin')).toBe('http://allowed.example');
+    expect(res.headers.get('Vary')).toBe('Origin');
+  });
+  
+  test('a disallowed origin gets no Allow-Origin but still Vary: Origin', async () => {
+    config.CORS_ORIGINS = ['http://allowed.example'];
+    
+    const res = await request('/health', { headers: { Origin: 'http://evil.example' } });
+    
+    expect(res.headers.get('Access-Control-Allow-Origin')).toBeNull();
+    expect(res.headers.get('Vary')).toBe('Origin');
+  });
+});
+
+describe('rate limiting', () => {
+  test('a flood of POST /events from one client gets 429 with a Retry-After', async () => {
+    config.RATE_LIMIT_RPS = 1;
+    config.RATE_LIMIT_BURST = 2;
+    config.RATE_LIMIT_JITTER_SECONDS = 0;
+    config.TRUST_PROXY = true;
+    
+    const statuses: number[] = [];
+    for (let i = 0; i < 4; i++) {
+      const res = await postJson('/events', hookEvent(), { 'X-Forwarded-For': '198.51.100.1' });
+      statuses.push(res.status);
+      if (res.status === 429) {
+        expect(res.headers.get('Retry-After')).toBe('1');
+      }
+    }
+    
+    expect(statuses).toEqual([200, 200, 429, 429]);
+    expect(getEventCount().count).toBe(2);
+  });
+  
+  test('the 429 Retry-After header includes the configured jitter', async () => {
+    config.RATE_LIMIT_RPS = 1;
+    config.RATE_LIMIT_BURST = 1;
+    config.RATE_LIMIT_JITTER_SECONDS = 2;
+    config.TRUST_PROXY = true;
+    
+    await postJson('/events', hookEvent(), { 'X-Forwarded-For': '198.51.100.2' });
+    const res = await postJson('/events', hookEvent(), { 'X-Forwarded-For': '198.51.100.2' });
+    
+    expect(res.status).toBe(429);
+    const retryAfter = Number(res.headers.get('Retry-After'));
+    expect(Number.isInteger(retryAfter)).toBe(true);
+    expect(retryAfter).toBeGreaterThanOrEqual(1);
+    expect(retryAfter).toBeLessThanOrEqual(3);
+  });
+});
+
+describe('GET /events/export', () => {
+  test('streams every stored event as one JSON object per line, oldest first', async () => {
+    await postJson('/events', hookEvent({ hook_event_type: 'PreToolUse' }));
+    await postJson('/events', hookEvent({ hook_event_type: 'PostToolUse' }));
+    await postJson('/events', hookEvent({ hook_event_type: 'Stop' }));
+    
+    const res = await request('/events/export');
+    
+    expect(res.status).toBe(200);
+    expect(res.headers.get('Content-Type')).toBe('application/x-ndjson');
+    const lines = (await res.text()).trimEnd().split('\n');
+    const events = lines.map(line => JSON.parse(line) as HookEvent);
+    expect(events.map(event => event.hook_event_type)).toEqual(['PreToolUse', 'PostToolUse', 'Stop']);
+    expect(events.every(event => typeof event.id === 'number' && event.session_id === 'session-1')).toBe(true);
+  });
+  
+  test('session_id narrows the export to one session', async () => {
+    await postJson('/events', hookEvent({ session_id: 'kept' }));
+    await postJson('/events', hookEvent({ session_id: 'skipped' }));
+    await postJson('/events', hookEvent({ session_id: 'kept' }));
+    
+    const res = await request('/events/export?session_id=kept');
+    const events = (await res.text()).trimEnd().split('\n').map(line => JSON.parse(line) as HookEvent);
+    
+    expect(events).toHaveLength(2);
+    expect(events.every(event => event.session_id === 'kept')).toBe(true);
+  });
+  
+  test('an empty store exports an empty body', async () => {
+    const res = await request('/events/export');
+    
+    expect(res.status).toBe(200);
+    expect(await res.text()).toBe('');
+  });
+});
+
+describe('FORWARD_WEBHOOK_URL', () => {
+  test('the webhook receives the event exactly as it was stored', async () => {
+    const webhook = mockWebhook();
+    config.FORWARD_WEBHOOK_URL = webhook.url;
+    try {
+      const stored = await (await postJson('/events', hookEvent({ session_id: 'forwarded' }))).json();
+      
+      await waitFor(() => webhook.received.length === 1);
+      expect(webhook.received[0]).toEqual(stored);
+    } finally {
+      webhook.stop();
+    }
+  });
+});
+
+describe('POST /events/batch', () => {
+  test('stores every event in the batch', async () => {
+    const batch = Array.from({ length: 5 }, (_, i) => hookEvent({ session_id: 'batch', payload: { step: i } }));
+    
+    const res = await postJson('/events/batch', batch);
+    
+    expect(res.status).toBe(200);
+    expect(await res.json()).toEqual({ accepted: 5 });
+    const stored = await (await request('/events?session_id=batch')).json() as HookEvent[];
+    expect(stored).toHaveLength(5);
+    expect(stored.map(event => event.payload.step).sort()).toEqual([0, 1, 2, 3, 4]);
+  });
+  
+  test('an incomplete event rejects the whole batch and names its index', async () => {
+    const { hook_event_type: _, ...incomplete } = hookEvent();
+    
+    const res = await postJson('/events/batch', [hookEvent(), incomplete, hookEvent()]);
+    
+    expect(res.status).toBe(400);
+    expect((await res.json()).error).toContain('index 1');
+    expect(getEventCount().count).toBe(0);
+  });
+  
+  test('more than 1000 events is rejected with 422', async () => {
+    const batch = Array.from({ length: 1001 }, () => hookEvent());
+    
+    const res = await postJson('/events/batch', batch);
+    
+    expect(res.status).toBe(422);
+    expect(getEventCount().count).toBe(0);
+  });
+  
+  test('a body that is not an array is rejected with 400', async () => {
+    const res = await postJson('/events/batch', hookEvent());
+    
+    expect(res.status).toBe(400);
+  });
+});
+
+describe('GET /events/export with a Range header', () => {
+  async function fullExport(): Promise<Uint8Array> {
+    return new Uint8Array(await (await request('/events/export')).arrayBuffer());
+  }
+  
+  test('bytes=0-9 returns the first ten bytes as 206', async () => {
+    await postJson('/events', hookEvent());
+    await postJson('/events', hookEvent());
+    const full = await fullExport();
+    
+    const res = await request('/events/export', { headers: { Range: 'bytes=0-9' } });
+    
+    expect(res.status).toBe(206);
+    expect(res.headers.get('Content-Range')).toBe(`bytes 0-9/${full.byteLength}`);
+    expect(new Uint8Array(await res.arrayBuffer())).toEqual(full.slice(0, 10));
+  });
+  
+  test('an open-ended range resumes from the offset to the end', async () => {
+    await postJson('/events', hookEvent());
+    await postJson('/events', hookEvent());
+    const full = await fullExport();
+    
+    const res = await request('/events/export', { headers: { Range: 'bytes=10-' } });
+    
+    expect(res.status).toBe(206);
+    expect(res.headers.get('Content-Range')).toBe(`bytes 10-${full.byteLength - 1}/${full.byteLength}`);
+    expect(new Uint8Array(await res.arrayBuffer())).toEqual(full.slice(10));
+  });
+  
+  test('a suffix range returns the last n bytes', async () => {
+    await postJson('/events', hookEvent());
+    const full = await fullExport();
+    
+    const res = await request('/events/export', { headers: { Range: 'bytes=-5' } });
+    
+    expect(res.status).toBe(206);
+    expect(new Uint8Array(await res.arrayBuffer())).toEqual(full.slice(-5));
+  });
+  
+  test('a range starting past the end is 416 with the total length', async () => {
+    await postJson('/events', hookEvent());
+    const full = await fullExport();
+    
+    const res = await request('/events/export', { headers: { Range: `bytes=${full.byteLength}-` } });
+    
+    expect(res.status).toBe(416);
+    expect(res.headers.get('Content-Range')).toBe(`bytes */${full.byteLength}`);
+  });
+  
+  test('If-Range with the current ETag resumes, and a stale one after an eviction gets the full body', async () => {
+    config.MAX_EVENTS = 2;
+    await postJson('/events', hookEvent({ payload: { step: 1 } }));
+    await postJson('/events', hookEvent({ payload: { step: 2 } }));
+    const first = await request('/events/export', { headers: { Range: 'bytes=0-9' } });
+    const etag = first.headers.get('ETag') ?? '';
+    expect(first.status).toBe(206);
+    expect(etag).not.toBe('');
+    
+    const resumed = await request('/events/export', { headers: { Range: 'bytes=10-', 'If-Range': etag } });
+    expect(resumed.status).toBe(206);
+    
+    // The third event evicts the first, so byte 10 no longer follows the bytes already downloaded
+    await postJson('/events', hookEvent({ payload: { step: 3 } }));
+    const full = await fullExport();
+    
+    const stale = await request('/events/export', { headers: { Range: 'bytes=10-', 'If-Range': etag } });
+    
+    expect(stale.status).toBe(200);
+    expect(stale.headers.get('ETag')).not.toBe(etag);
+    expect(new Uint8Array(await stale.arrayBuffer())).toEqual(full);
+  });
+  
+  test('If-Match with a stale ETag is 412', async () => {
+    await postJson('/events', hookEvent());
+    const etag = (await request('/events/export')).headers.get('ETag') ?? '';
+    expect((await request('/events/export', { headers: { 'If-Match': etag } })).status).toBe(200);
+    
+    const [event] = await (await request('/events')).json() as HookEvent[];
+    await request(`/events/${event?.id}`, {
+      method: 'PATCH',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ note: 'changes the exported bytes' })
+    });
+    
+    const res = await request('/events/export', { headers: { Range: 'bytes=0-9', 'If-Match': etag } });
+    expect(res.status).toBe(412);
+  });
+  
+  test('a multi-range header is ignored and the full export sent', async () => {
+    await postJson('/events', hookEvent());
+    const full = await fullExport();
+    
+    const res = await request('/events/export', { headers: { Range: 'bytes=0-1,4-5' } });
+    
+    expect(res.status).toBe(200);
+    expect(new Uint8Array(await res.arrayBuffer())).toEqual(full);
+  });
+});
+
+// A request body that only arrives when the test says so, for holding a handler mid-read
+function stalledBody(): { body: ReadableStream<Uint8Array>; send: (text: string) => void; finish: () => void } {
+  const encoder = new TextEncoder();
+  let controller!: ReadableStreamDefaultController<Uint8Array>;
+  const body = new ReadableStream<Uint8Array>({
+    start(c) {
+      controller = c;
+    }
+  });
+  return {
+    body,
+    send: (text) => controller.enqueue(encoder.encode(text)),
+    finish: () => controller.close()
+  };
+}
+
+describe('REQUEST_TIMEOUT_MS', () => {
+  test('a handler stuck reading its body gets 408 and stores nothing once the body completes', async () => {
+    config.REQUEST_TIMEOUT_MS = 50;
+    const stalled = stalledBody();
+    const event = JSON.stringify(hookEvent());
+    
+    const pending = request('/events', { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: stalled.body });
+    stalled.send(event.slice(0, 10));
+    const res = await pending;
+    
+    expect(res.status).toBe(408);
+    
+    // The abandoned handler finishes reading after its client was answered: it must not write
+    stalled.send(event.slice(10));
+    stalled.finish();
+    await Bun.sleep(20);
+    expect(getEventCount().count).toBe(0);
+  });
+  
+  test('with DEBUG_BODY_LOG on, the 408 is not held back waiting for the stalled body', async () => {
+    config.REQUEST_TIMEOUT_MS = 50;
+    config.DEBUG_BODY_LOG = true;
+    const stalled = stalledBody();
+    
+    const pending = request('/events', { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: stalled.body });
+    stalled.send('{"source_app"');
+    const res = await Promise.race([pending, Bun.sleep(500).then(() => undefined)]);
+    
+    expect(res?.status).toBe(408);
+    stalled.finish();
+  });
+  
+  test('a handler that finishes in time is unaffected', async () => {
+    config.REQUEST_TIMEOUT_MS = 1000;
+    
+    const res = await postJson('/events', hookEvent());
+    
+    expect(res.status).toBe(200);
+    expect(getEventCount().count).toBe(1);
+  });
+});
+
+describe('MAX_CONNECTIONS', () => {
+  test('the slot stays taken until the handler itself settles, even after its 408', async () => {
+    config.MAX_CONNECTIONS = 1;
+    config.REQUEST_TIMEOUT_MS = 50;
+    const stalled = stalledBody();
+    
+    const pending = request('/events', { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: stalled.body });
+    expect((await request('/events')).status).toBe(503);
+    
+    expect((await pending).status).toBe(408);
+    const busy = await request('/events');
+    expect(busy.status).toBe(503);
+    expect(busy.headers.get('Retry-After')).toBe('1');
+    
+    stalled.send(JSON.stringify(hookEvent()));
+    stalled.finish();
+    await Bun.sleep(20);
+    expect((await request('/events')).status).toBe(200);
+  });
+  
+  test('health checks are exempt from the limit', async () => {
+    config.MAX_CONNECTIONS = 1;
+    const stalled = stalledBody();
+    
+    const pending = request('/events', { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: stalled.body });
+    expect((await request('/health')).status).toBe(200);
+    
+    stalled.send(JSON.stringify(hookEvent()));
+    stalled.finish();
+    expect((await pending).status).toBe(200);
+  });
+});
+
+describe('GET /sessions/:session_id/timeline', () => {
+  test('lists the session in order with the time since the previous event', async () => {
+    await postJson('/events', hookEvent({ session_id: 'timeline', timestamp: 4000 }));
+    await postJson('/events', hookEvent({ session_id: 'timeline', timestamp: 1000 }));
+    await postJson('/events', hookEvent({ session_id: 'timeline', timestamp: 1500 }));
+    await postJson('/events', hookEvent({ session_id: 'other', timestamp: 2000 }));
+    
+    const res = await request('/sessions/timeline/timeline');
+    
+    expect(res.status).toBe(200);
+    const body = await res.json();
+    expect(body.session_id).toBe('timeline');
+    expect(body.events.map((event: { timestamp: number; delta_ms: number }) => [event.timestamp, event.delta_ms])).toEqual([
+      [1000, 0],
+      [1500, 500],
+      [4000, 2500]
+    ]);
+  });
+  
+  test('decodes a percent-encoded session id', async () => {
+    await postJson('/events', hookEvent({ session_id: 'a b/c' }));
+    
+    const res = await request(`/sessions/${encodeURIComponent('a b/c')}/timeline`);
+    
+    expect(res.status).toBe(200);
+    expect((await res.json()).session_id).toBe('a b/c');
+  });
+  
+  test('an unknown session is 404', async () => {
+    const res = await request('/sessions/missing/timeline');
+    
+    expect(res.status).toBe(404);
+    expect(await res.json()).toEqual({ error: 'session not found', session_id: 'missing' });
+  });
+  
+  test('malformed percent-encoding is 400, not a 500', async () => {
+    const res = await request('/sessions/%E0/timeline');
+    
+    expect(res.status).toBe(400);
+    expect((await res.json()).error).toBe('invalid session id: malformed percent-encoding');
+  });
+});
+
+describe('theme routes', () => {
+  test('GET /api/themes/stats answers with the stats, not a lookup of theme "stats"', async () => {
+    const res = await request('/api/themes/stats');
+    
+    expect(res.status).toBe(200);
+    expect((await res.json()).data.totalThemes).toBe(0);
+  });
+  
+  test('DELETE on an export path is 405 instead of deleting the theme', async () => {
+    const res = await request('/api/themes/some-theme/export', { method: 'DELETE' });
+    
+    expect(res.status).toBe(405);
+    expect(res.headers.get('Allow')).toBe('GET, HEAD');
+  });
+  
+  test('GET /api/themes/import is 405, since import only takes POST', async () => {
+    expect((await request('/api/themes/import')).status).toBe(405);
+  });
+  
+  test('an unknown theme id is 404', async () => {
+    expect((await request('/api/themes/missing')).status).toBe(404);
+  });
+});
+
+describe('GET /health', () => {
+  test('uptime_seconds counts from the start time', async () => {
+    setStartedAt(Date.now() - 100 * 1000);
+    
+    const body = await (await request('/health')).json();
+    
+    expect(body.status).toBe('healthy');
+    expect(body.uptime_seconds).toBeGreaterThanOrEqual(100);
+  });
+  
+  test('reports a non-empty version next to the status and timestamp', async () => {
+    const body = await (await request('/health')).json();
+    
+    expect(typeof body.version).toBe('string');
+    expect(body.version).not.toBe('');
+    expect(Number.isNaN(Date.parse(body.timestamp))).toBe(false);
+    expect(Number.isInteger(body.uptime_seconds)).toBe(true);
+  });
+});
+
+describe('GET /health/ready', () => {
+  test('is ready while the database answers', async () => {
+    const res = await request('/health/ready');
+    
+    expect(res.status).toBe(200);
+    const body = await res.json();
+    expect(body.status).toBe('ready');
+    expect(body.checks).toEqual([{ name: 'database', status: 'ok' }]);
+  });
+  
+  test('is 503 with a failing database check once the database is closed', async () => {
+    closeDatabase();
+    
+    const res = await request('/health/ready');
+    
+    expect(res.status).toBe(503);
+    const body = await res.json();
+    expect(body.status).toBe('not_ready');
+    expect(body.checks).toEqual([{ name: 'database', status: 'failing', error: 'database not initialized' }]);
+  });
+});
+
+describe('disabled routes', () => {
+  async function listedPaths(): Promise<string[]> {
+    const body = await (await request('/')).json();
+    return body.endpoints.map((endpoint: { path: string }) => endpoint.path);
+  }
+  
+  test('the admin route is neither listed nor reachable without ENABLE_ADMIN', async () => {
+    expect(await listedPaths()).not.toContain('/admin/shutdown');
+    expect((await request('/admin/shutdown', { method: 'POST' })).status).toBe(404);
+    // 404 rather than 405: a disabled route looks like an unknown path whatever the method
+    expect((await request('/admin/shutdown')).status).toBe(404);
+  });
+});
+
+describe('PATCH /events/:id', () => {
+  function patchJson(path: string, body: unknown): Promise<Response> {
+    return request(path, {
+      method: 'PATCH',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify(body)
+    });
+  }
+  
+  test('adds tags and a note without touching the original fields', async () => {
+    const original: HookEvent = await (await postJson('/events', hookEvent({ session_id: 'annotated', timestamp: 1234 }))).json();
+    
+    const res = await patchJson(`/events/${original.id}`, { tags: ['slow', 'retry'], note: 'looked into it' });
+    
+    expect(res.status).toBe(200);
+    expect(await res.json()).toEqual({ ...original, tags: ['slow', 'retry'], note: 'looked into it' });
+    const [stored] = await (await request('/events?session_id=annotated')).json() as HookEvent[];
+    expect(stored).toEqual({ ...original, tags: ['slow', 'retry'], note: 'looked into it' });
+  });
+  
+  test('merges with earlier annotations, and a null note clears the note', async () => {
+    const { id } = await (await postJson('/events', hookEvent())).json();
+    await patchJson(`/events/${id}`, { tags: ['first'], note: 'draft' });
+    
+    const res = await patchJson(`/events/${id}`, { note: null });
+    
+    const body = await res.json();
+    expect(body.tags).toEqual(['first']);
+    expect(body.note).toBeUndefined();
+  });
+  
+  test('an unknown id is 404', async () => {
+    const res = await patchJson('/events/999999', { tags: ['x'] });
+    
+    expect(res.status).toBe(404);
+    expect(await res.json()).toEqual({ error: 'event not found', id: 999999 });
+  });
+  
+  test('tags that are not strings are 400', async () => {
+    const { id } = await (await postJson('/events', hookEvent())).json();
+    
+    expect((await patchJson(`/events/${id}`, { tags: [1, 2] })).status).toBe(400);
+  });
+  
+  test('ingestion stores and echoes only the event columns', async () => {
+    const res = await postJson('/events', { ...hookEvent(), tags: ['smuggled'], note: 'smuggled', extra: 'dropped' });
+    
+    const body = await res.json();
+    expect(body.extra).toBeUndefined();
+    expect(body.tags).toBeUndefined();
+    expect(body.note).toBeUndefined();
+  });
+});
+
+describe('GET /debug/state', () => {
+  test('is 404 and unlisted unless ENABLE_DEBUG is on', async () => {
+    const body = await (await request('/')).json();
+    
+    expect(body.endpoints.map((endpoint: { path: string }) => endpoint.path)).not.toContain('/debug/state');
+    expect((await request('/debug/state')).status).toBe(404);
+    expect((await request('/debug/state', { method: 'POST' })).status).toBe(404);
+  });
+  
+  test('reports event counts, subscribers and uptime when enabled', async () => {
+    config.ENABLE_DEBUG = true;
+    await postJson('/events', hookEvent());
+    
+    const res = await request('/debug/state');
+    
+    expect(res.status).toBe(200);
+    const body = await res.json();
+    expect(Object.keys(body).sort()).toEqual(['events', 'subscribers', 'uptime_seconds']);
+    expect(body.events.count).toBe(1);
+    expect(body.subscribers).toEqual({ websocket: 0, sse: 0 });
+    expect(body.uptime_seconds).toBeGreaterThanOrEqual(0);
+  });
+});
+
+describe('HEAD requests', () => {
+  test('resolve the client address from the original request, not the GET copy', async () => {
+    const asked: Request[] = [];
+    const recordingServer = {
+      ...server,
+      requestIP: (req: Request) => {
+        asked.push(req);
+        return { address: '203.0.113.8', family: 'IPv4', port: 51000 };
+      }
+    } as unknown as Server;
+    const req = new Request('http://localhost/health', { method: 'HEAD' });
+    
+    const res = await handleFetch(req, recordingServer);
+    
+    expect(res?.status).toBe(200);
+    expect(await res?.text()).toBe('');
+    expect(asked.length).toBeGreaterThan(0);
+    expect(asked.every(seen => seen === req)).toBe(true);
+  });
+  
+  test('HEAD /events is 200 with no body and the Content-Length of the GET body', async () => {
+    await postJson('/events', hookEvent());
+    const get = await request('/events');
+    
+    const res = await request('/events', { method: 'HEAD' });
+    
+    expect(res.status).toBe(200);
+    expect(await res.text()).toBe('');
+    expect(res.headers.get('Content-Length')).toBe(String((await get.arrayBuffer()).byteLength));
+  });
+  
+  test.each(['/events/stats', '/events/count', '/events/filter-options', '/sessions'])('HEAD %s matches the GET status and Content-Length', async (path) => {
+    await postJson('/events', hookEvent());
+    const get = await request(path);
+    
+    const res = await request(path, { method: 'HEAD' });
+    
+    expect(res.status).toBe(get.status);
+    expect(await res.text()).toBe('');
+    expect(res.headers.get('Content-Length')).toBe(String((await get.arrayBuffer()).byteLength));
+  });
+  
+  test('HEAD on the streaming export is 405 rather than a buffered body', async () => {
+    const res = await request('/events/export', { method: 'HEAD' });
+    
+    expect(res.status).toBe(405);
+    expect(res.headers.get('Allow')).toBe('GET');
+  });
+});
+
+describe('STRICT_EVENTS', () => {
+  test('an unknown hook type is rejected with 422 in strict mode', async () => {
+    config.STRICT_EVENTS = true;
+    
+    const res = await postJson('/events', hookEvent({ hook_event_type: 'MadeUpHook' }));
+    
+    expect(res.status).toBe(422);
+    expect(getEventCount().count).toBe(0);
+  });
+  
+  test('known hook types are still accepted in strict mode', async () => {
+    config.STRICT_EVENTS = true;
+    
+    expect((await postJson('/events', hookEvent({ hook_event_type: 'Stop' }))).status).toBe(200);
+  });
+  
+  test('an unknown hook type is accepted when strict mode is off', async () => {
+    config.STRICT_EVENTS = false;
+    
+    const res = await postJson('/events', hookEvent({ hook_event_type: 'MadeUpHook' }));
+    
+    expect(res.status).toBe(200);
+    expect(getEventCount().count).toBe(1);
+  });
+  
+  test('a batch with an unknown hook type names its index in strict mode', async () => {
+    config.STRICT_EVENTS = true;
+    
+    const res = await postJson('/events/batch', [hookEvent(), hookEvent({ hook_event_type: 'MadeUpHook' })]);
+    
+    expect(res.status).toBe(422);
+    expect((await res.json()).error).toContain('index 1');
+    expect(getEventCount().count).toBe(0);
+  });
+});
+
+describe('NORMALIZE_SOURCE_APP', () => {
+  test('agent names are stored exactly as sent by default', async () => {
+    const res = await postJson('/events', hookEvent({ source_app: ' Claude ' }));
+    
+    expect((await res.json()).source_app).toBe(' Claude ');
+  });
+  
+  test('when enabled, names are trimmed and lowercased so they group as one agent', async () => {
+    config.NORMALIZE_SOURCE_APP = true;
+    
+    const res = await postJson('/events', hookEvent({ source_app: ' Claude ' }));
+    
+    expect((await res.json()).source_app).toBe('claude');
+  });
+});
+
+describe('BASE_PATH', () => {
+  test('routes are served under the prefix and nowhere else', async () => {
+    config.BASE_PATH = '/obs';
+    
+    expect((await request('/obs/health')).status).toBe(200);
+    expect((await request('/obs/v1/health')).status).toBe(200);
+    expect((await request('/health')).status).toBe(404);
+    expect((await request('/observability/health')).status).toBe(404);
+  });
+  
+  test('the OpenAPI spec advertises the prefix', async () => {
+    config.BASE_PATH = '/obs';
+    
+    const spec = await (await request('/obs/openapi.json')).json();
+    
+    expect(spec.servers).toEqual([{ url: '/obs' }]);
+  });
+  
+  test('an empty BASE_PATH serves at the root', async () => {
+    expect((await request('/health')).status).toBe(200);
+  });
+});
+
+describe('responses_total', () => {
+  // Current value of one counter sample, 0 when the series hasn't been emitted yet
+  function counter(series: string): number {
+    const line = renderMetrics({ websocket: 0, sse: 0 }).split('\n').find(line => line.startsWith(`${series} `));
+    return line ? Number(line.slice(series.length + 1)) : 0;
+  }
+  
+  test('counts every response by exact status and by class', async () => {
+    const before = {
+      ok: counter('observability_responses_total{status="200"}'),
+      notFound: counter('observability_responses_total{status="404"}'),
+      success: counter('observability_response_classes_total{class="2xx"}'),
+      clientError: counter('observability_response_classes_total{class="4xx"}')
+    };
+    
+    expect((await request('/health')).status).toBe(200);
+    expect((await request('/no-such-route')).status).toBe(404);
+    
+    expect(counter('observability_responses_total{status="200"}')).toBe(before.ok + 1);
+    expect(counter('observability_responses_total{status="404"}')).toBe(before.notFound + 1);
+    expect(counter('observability_response_classes_total{class="2xx"}')).toBe(before.success + 1);
+    expect(counter('observability_response_classes_total{class="4xx"}')).toBe(before.clientError + 1);
+  });
+  
+  test('counts errors returned before routing, such as a failed authentication', async () => {
+    config.API_KEY = 'metrics-key';
+    const before = counter('observability_responses_total{status="401"}');
+    
+    expect((await request('/events')).status).toBe(401);
+    
+    expect(counter('observability_responses_total{status="401"}')).toBe(before + 1);
+  });
+});
+
+describe('unknown routes', () => {
+  test('get 404 with a JSON body naming the path', async () => {
+    const res = await request('/does-not-exist');
+    
+    expect(res.status).toBe(404);
+    expect(res.headers.get('Content-Type')).toBe('application/json');
+    expect(await res.json()).toEqual({ error: 'route not found', path: '/does-not-exist' });
+  });
+});
+
+describe('response compression', () => {
+  test('a large JSON response is gzipped when the client accepts it', async () => {
+    for (let i = 0; i < 20; i++) {
+      await postJson('/events', hookEvent({ session_id: 'compressed', payload: { tool_name: 'Bash', command: `echo ${'x'.repeat(100)}` } }));
+    }
+    
+    const res = await request('/events?session_id=compressed', { headers: { 'Accept-Encoding': 'gzip' } });
+    
+    expect(res.headers.get('Content-Encoding')).toBe('gzip');
+    const events = JSON.parse(new TextDecoder().decode(Bun.gunzipSync(new Uint8Array(await res.arrayBuffer()))));
+    expect(events).toHaveLength(20);
+  });
+  
+  test('a response below the threshold is sent as is', async () => {
+    const res = await request('/health', { headers: { 'Accept-Encoding': 'gzip' } });
+    
+    expect(res.headers.get('Content-Encoding')).toBeNull();
+    expect((await res.json()).status).toBe('healthy');
+  });
+});
+
+describe('MAX_EVENTS', () => {
+  test('storing past the cap evicts the oldest events and keeps the count at the cap', async () => {
+    config.MAX_EVENTS = 3;
+    const ids: number[] = [];
+    for (let i = 0; i < 5; i++) {
+      ids.push((await (await postJson('/events', hookEvent({ payload: { step: i } }))).json()).id);
+    }
+    
+    const count = await (await request('/events/count')).json();
+    expect(count.count).toBe(3);
+    expect(count.capacity).toBe(3);
+    expect(count.evicted).toBeGreaterThanOrEqual(2);
+    
+    const lines = (await (await request('/events/export')).text()).trimEnd().split('\n');
+    expect(lines.map(line => (JSON.parse(line) as HookEvent).id)).toEqual(ids.slice(2));
+  });
+});
+
+describe('GET /health content negotiation', () => {
+  test('answers JSON by default', async () => {
+    const res = await request('/health');
+    
+    expect(res.headers.get('Content-Type')).toBe('application/json');
+    expect((await res.json()).status).toBe('healthy');
+  });
+  
+  test('answers a one-line text body when the client asks for text/plain', async () => {
+    const res = await request('/health', { headers: { Accept: 'text/plain' } });
+    
+    expect(res.headers.get('Content-Type')).toBe('text/plain; charset=utf-8');
+    expect(await res.text()).toBe('status: healthy');
+  });
+});
+
+describe('wrong methods', () => {
+  test('POST /health is 405 with an Allow header and a JSON body', async () => {
+    const res = await request('/health', { method: 'POST' });
+    
+    expect(res.status).toBe(405);
+    expect(res.headers.get('Allow')).toBe('GET, HEAD');
+    expect(await res.json()).toEqual({ error: 'method not allowed', method: 'POST', allowed: ['GET', 'HEAD'] });
+  });
+});
+
+describe('GET /events/stats', () => {
+  test('groups the stored events by type and source app', async () => {
+    await postJson('/events', hookEvent({ source_app: 'alpha', hook_event_type: 'PreToolUse', timestamp: 1000 }));
+    await postJson('/events', hookEvent({ source_app: 'alpha', hook_event_type: 'PostToolUse', timestamp: 3000 }));
+    await postJson('/events', hookEvent({ source_app: 'beta', hook_event_type: 'PreToolUse', timestamp: 2000 }));
+    
+    const res = await request('/events/stats');
+    
+    expect(res.status).toBe(200);
+    expect(await res.json()).toEqual({
+      total: 3,
+      by_event_type: { PreToolUse: 2, PostToolUse: 1 },
+      by_source_app: { alpha: 2, beta: 1 },
+      latest_timestamp: 3000
+    });
+  });
+});
+
+describe('handler errors', () => {
+  test('a handler that throws gets a 500 JSON body instead of a dropped connection', async () => {
+    // Every query against a closed database throws
+    closeDatabase();
+    
+    const res = await request('/events');
+    
+    expect(res.status).toBe(500);
+    expect(res.headers.get('Content-Type')).toBe('application/json');
+    expect(await res.json()).toEqual({ error: 'internal server error' });
+  });
+});
+
+describe('GET /events with since and until', () => {
+  test('keeps the events in [since, until)', async () => {
+    for (const timestamp of ['2025-01-01T00:00:00Z', '2025-01-02T00:00:00Z', '2025-01-03T00:00:00Z', '2025-01-04T00:00:00Z']) {
+      await postJson('/events', hookEvent({ session_id: 'window', timestamp: Date.parse(timestamp) }));
+    }
+    
+    const res = await request('/events?session_id=window&since=2025-01-02T00:00:00Z&until=2025-01-04T00:00:00Z');
+    
+    expect(res.status).toBe(200);
+    const events = await res.json() as HookEvent[];
+    expect(events.map(event => new Date(event.timestamp!).toISOString())).toEqual([
+      '2025-01-03T00:00:00.000Z',
+      '2025-01-02T00:00:00.000Z'
+    ]);
+  });
+  
+  test('a since that is not an RFC 3339 timestamp is 400', async () => {
+    const res = await request('/events?since=yesterday');
+    
+    expect(res.status).toBe(400);
+    expect((await res.json()).error).toBe("invalid query parameter: since must be an RFC 3339 timestamp such as 2025-01-31T12:00:00Z, got 'yesterday'");
+  });
+});
+
+describe('malformed JSON bodies', () => {
+  test('are 400 with an invalid json error', async () => {
+    const res = await request('/events', { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: '{bad json' });
+    
+    expect(res.status).toBe(400);
+    expect(res.headers.get('Content-Type')).toBe('application/json');
+    expect((await res.json()).error).toStartWith('invalid json: ');
+  });
+});
+
+describe('GET /health/ready with a full store', () => {
+  test('reports degraded with the buffer fill once the store is full and evicting', async () => {
+    config.MAX_EVENTS = 2;
+    for (let i = 0; i < 3; i++) {
+      await postJson('/events', hookEvent());
+    }
+    
+    const res = await request('/health/ready');
+    
+    expect(res.status).toBe(200);
+    expect(await res.json()).toMatchObject({ status: 'degraded', event_buffer_used: 2, event_buffer_capacity: 2 });
+  });
+  
+  test('a store below its cap is ready', async () => {
+    config.MAX_EVENTS = 2;
+    await postJson('/events', hookEvent());
+    
+    expect((await (await request('/health/ready')).json()).status).toBe('ready');
+  });
+});
+
+describe('POST /events/batch with Content-Encoding: gzip', () => {
+  function postGzip(body: string): Promise<Response> {
+    return request('/events/batch', {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json', 'Content-Encoding': 'gzip' },
+      body: Bun.gzipSync(new TextEncoder().encode(body))
+    });
+  }
+  
+  test('inflates the body before parsing and stores the events', async () => {
+    const res = await postGzip(JSON.stringify([hookEvent({ session_id: 'gzipped' }), hookEvent({ session_id: 'gzipped' })]));
+    
+    expect(res.status).toBe(200);
+    expect(await res.json()).toEqual({ accepted: 2 });
+    expect(await (await request('/events?session_id=gzipped')).json()).toHaveLength(2);
+  });
+  
+  test('a body that inflates past MAX_BODY_BYTES is 413', async () => {
+    config.MAX_BODY_BYTES = 1024;
+    
+    // Compresses to well under the limit, but inflates far past it
+    const res = await postGzip(JSON.stringify([hookEvent({ payload: { text: 'x'.repeat(10000) } })]));
+    
+    expect(res.status).toBe(413);
+    expect((await res.json()).error).toBe('decompressed body exceeds 1024 bytes');
+    expect(getEventCount().count).toBe(0);
+  });
+});
+
+describe('invalid query parameters', () => {
+  test('limit=abc is 400 with a JSON error naming the parameter', async () => {
+    const res = await request('/events?limit=abc');
+    
+    expect(res.status).toBe(400);
+    expect(await res.json()).toEqual({ error: "invalid query parameter: limit must be an integer of at least 1, got 'abc'" });
+  });
+});
+
+describe('GET /events/search', () => {
+  test('matches payload text case-insensitively, newest first', async () => {
+    await postJson('/events', hookEvent({ payload: { command: 'npm run Build' }, timestamp: 1000 }));
+    await postJson('/events', hookEvent({ payload: { command: 'git status' }, timestamp: 2000 }));
+    await postJson('/events', hookEvent({ payload: { command: 'cargo build' }, timestamp: 3000 }));
+    
+    const res = await request('/events/search?q=build');
+    
+    expect(res.status).toBe(200);
+    const events = await res.json() as HookEvent[];
+    expect(events.map(event => event.payload.command)).toEqual(['cargo build', 'npm run Build']);
+  });
+  
+  test('an empty q is 400', async () => {
+    expect((await request('/events/search?q=%20')).status).toBe(400);
+  });
+});
+
+describe('CORS preflight', () => {
+  test('sends Max-Age and Allow-Credentials as configured', async () => {
+    config.CORS_ORIGINS = ['http://dashboard.example'];
+    config.CORS_ALLOW_CREDENTIALS = true;
+    config.CORS_MAX_AGE_SECONDS = 600;
+    
+    const res = await request('/events', { method: 'OPTIONS', headers: { Origin: 'http://dashboard.example' } });
+    
+    expect(res.headers.get('Access-Control-Max-Age')).toBe('600');
+    expect(res.headers.get('Access-Control-Allow-Credentials')).toBe('true');
+    expect(res.headers.get('Access-Control-Allow-Origin')).toBe('http://dashboard.example');
+  });
+  
+  test('sends neither header by default', async () => {
+    const res = await request('/events', { method: 'OPTIONS', headers: { Origin: 'http://dashboard.example' } });
+    
+    expect(res.headers.get('Access-Control-Max-Age')).toBeNull();
+    expect(res.headers.get('Access-Control-Allow-Credentials')).toBeNull();
+  });
+});
+
+describe('MAX_EVENT_PAYLOAD_BYTES', () => {
+  test('one oversized payload rejects the batch with 422 naming its index', async () => {
+    config.MAX_EVENT_PAYLOAD_BYTES = 100;
+    
+    const res = await postJson('/events/batch', [hookEvent(), hookEvent({ payload: { text: 'x'.repeat(200) } }), hookEvent()]);
+    
+    expect(res.status).toBe(422);
+    expect((await res.json()).error).toBe('Payload of event at index 1 exceeds 100 bytes');
+    expect(getEventCount().count).toBe(0);
+  });
+});
+
+describe('ACCESS_LOG', () => {
+  test('writes one line per request with method, path, status, latency, client and request id', async () => {
+    config.ACCESS_LOG = true;
+    
+    const entries = await accessLog(() => request('/health', { headers: { 'X-Request-Id': 'access-1' } }));
+    
+    expect(entries).toHaveLength(1);
+    expect(entries[0]).toMatchObject({
+      level: 'info',
+      target: 'access',
+      message: 'request completed',
+      method: 'GET',
+      path: '/health',
+      status: 200,
+      client_ip: '203.0.113.7',
+      request_id: 'access-1'
+    });
+    expect(typeof entries[0]?.latency_ms).toBe('number');
+  });
+  
+  test('writes nothing when ACCESS_LOG is off', async () => {
+    expect(await accessLog(() => request('/health'))).toEqual([]);
+  });
+});
+
+describe('TRUST_PROXY', () => {
+  async function loggedClientIp(headers: Record<string, string>): Promise<unknown> {
+    config.ACCESS_LOG = true;
+    const [entry] = await accessLog(() => request('/health', { headers }));
+    return entry?.client_ip;
+  }
+  
+  test('a forged X-Forwarded-For is ignored when TRUST_PROXY is off', async () => {
+    config.TRUST_PROXY = false;
+    
+    expect(await loggedClientIp({ 'X-Forwarded-For': '198.51.100.99' })).toBe('203.0.113.7');
+  });
+  
+  test('behind a trusted proxy the first X-Forwarded-For address is the client', async () => {
+    config.TRUST_PROXY = true;
+    
+    expect(await loggedClientIp({ 'X-Forwarded-For': '198.51.100.99, 10.0.0.1' })).toBe('198.51.100.99');
+  });
+});
+
+describe('GET /ping', () => {
+  test('answers exactly pong as text/plain', async () => {
+    const res = await request('/ping');
+    
+    expect(res.status).toBe(200);
+    expect(res.headers.get('Content-Type')).toBe('text/plain; charset=utf-8');
+    expect(await res.text()).toBe('pong');
+  });
+  
+  test('is answered before authentication', async () => {
+    config.API_KEY = 'ping-key';
+    
+    expect((await request('/ping')).status).toBe(200);
+  });
+});
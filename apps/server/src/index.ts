@@ -1,5 +1,9 @@
-import { initDatabase, insertEvent, getFilterOptions, getRecentEvents } from './db';
-import type { HookEvent } from './types';
+import { initDatabase, closeDatabase, pingDatabase, evictExpiredEvents, getEventById, getFilterOptions, getRecentEvents, iterateEvents, exportVersion, getEventCount, getSessions, getSessionEvents } from './db';
+import { sqliteEventSink, fileEventSink, teeEventSink } from './eventSink';
+import type { EventSink } from './eventSink';
+import type { Server } from 'bun';
+import { gunzipSync } from 'node:zlib';
+import type { HookEvent, EventAnnotation, ReadinessCheck, TimelineEntry } from './types';
 import { 
   createTheme, 
   updateThemeById, 
@@ -11,315 +15,1415 @@ import {
   getThemeStats 
 } from './theme';
 import { config, validateRequiredConfig } from './config';
+import { recordRequest, recordRequestDuration, recordResponse, recordEventIngested, recordLaggingSubscriberDropped, renderMetrics } from './metrics';
+import { matchRoute, routes } from './routes';
+import type { RouteDefinition } from './routes';
+import { authenticate, isAuthEnabled } from './auth';
+import type { JwtClaims } from './auth';
+import { takeToken } from './rateLimit';
+import { lookupIdempotencyKey, rememberIdempotencyKey } from './idempotency';
+import { forwardEvent } from './forwarder';
+import { buildOpenApiSpec } from './openapi';
+import { SWAGGER_UI_HTML } from './docs';
+import { createLogger } from './logger';
+import { buildInfo } from './version';
+import { logBodies } from './bodyLog';
+import packageJson from '../package.json';
+
+const logger = createLogger('server');
+const accessLogger = createLogger('access');
 
 // Validate configuration and initialize database
 validateRequiredConfig();
 initDatabase();
 
+// Storage backend for ingested events
+const eventSink: EventSink = config.EVENT_ARCHIVE_FILE
+  ? teeEventSink([sqliteEventSink, fileEventSink(config.EVENT_ARCHIVE_FILE)])
+  : sqliteEventSink;
+
+// Per-connection data attached when /stream upgrades
+interface StreamSocketData {
+  sinceId?: number;
+}
+
 // Store WebSocket clients
 const wsClients = new Set<any>();
 
-// Create Bun server with HTTP and WebSocket support
-const server = Bun.serve({
-  port: config.PORT,
+// Store Server-Sent Events clients
+interface SseClient {
+  controller: ReadableStreamDefaultController<Uint8Array>;
+  keepAlive: ReturnType<typeof setInterval>;
+}
+const sseClients = new Set<SseClient>();
+const sseEncoder = new TextEncoder();
+
+// Comment lines sent on idle SSE streams so proxies don't time them out
+const SSE_KEEPALIVE_MS = 15000;
+
+// A subscriber with more than this much unsent data is too slow to keep up
+const MAX_SUBSCRIBER_BUFFER_BYTES = 1024 * 1024;
+
+function removeSseClient(client: SseClient): void {
+  clearInterval(client.keepAlive);
+  sseClients.delete(client);
+}
+
+function sseSend(client: SseClient, chunk: string): void {
+  try {
+    client.controller.enqueue(sseEncoder.encode(chunk));
+  } catch (err) {
+    // Stream already closed
+    removeSseClient(client);
+  }
+}
+
+// Fan an event out to every WebSocket and SSE client. Slow clients are disconnected
+// (and counted) instead of letting their send buffers grow without bound; ingestion
+// itself never waits on a subscriber.
+function broadcastEvent(event: HookEvent): void {
+  const message = JSON.stringify({ type: 'event', data: event });
+  
+  sseClients.forEach(client => {
+    const { desiredSize } = client.controller;
+    if (desiredSize !== null && desiredSize <= 0) {
+      logger.warn('Disconnecting SSE client that is not keeping up');
+      recordLaggingSubscriberDropped('sse');
+      sseSend(client, `event: disconnect\ndata: ${JSON.stringify({ reason: 'Client too slow' })}\n\n`);
+      removeSseClient(client);
+      try {
+        client.controller.close();
+      } catch (err) {
+        // Stream already closed
+      }
+      return;
+    }
+    sseSend(client, `event: hook_event\ndata: ${JSON.stringify(event)}\n\n`);
+  });
+  
+  wsClients.forEach(client => {
+    try {
+      const sent = client.send(message);
+      if (sent === 0) {
+        // Message dropped: the connection is already gone
+        wsClients.delete(client);
+      } else if (client.getBufferedAmount() > MAX_SUBSCRIBER_BUFFER_BYTES) {
+        logger.warn('Disconnecting WebSocket client that is not keeping up');
+        recordLaggingSubscriberDropped('websocket');
+        wsClients.delete(client);
+        client.close(1013, 'Client too slow');
+      }
+    } catch (err) {
+      // Client disconnected, remove from set
+      wsClients.delete(client);
+    }
+  });
+}
+
+// Process start time, used for uptime reporting
+let startedAt = Date.now();
+
+// Move the uptime origin, e.g. so tests can check uptime without waiting for it
+export function setStartedAt(timestamp: number): void {
+  startedAt = timestamp;
+}
+
+// API docs are public, so don't expose them on authenticated production deployments
+const docsEnabled = config.NODE_ENV !== 'production' || !isAuthEnabled();
+
+// Admin endpoints can stop the server, so they are never exposed without authentication
+const adminEnabled = config.ENABLE_ADMIN && isAuthEnabled();
+if (config.ENABLE_ADMIN && !adminEnabled) {
+  logger.warn('⚠️  ENABLE_ADMIN is set but authentication is disabled; admin endpoints stay off until API_KEY or AUTH_MODE=jwt is configured');
+}
+
+// Build CORS headers - only echo the origin back when it is on the allowlist
+function corsHeaders(req: Request): Record<string, string> {
+  const allowedOrigins = Array.isArray(config.CORS_ORIGINS) ? config.CORS_ORIGINS : [config.CORS_ORIGINS];
+  const requestOrigin = req.headers.get('origin');
+  const headers: Record<string, string> = {
+    'Access-Control-Allow-Methods': 'GET, POST, PUT, PATCH, DELETE, OPTIONS',
+    'Access-Control-Allow-Headers': 'Content-Type, Content-Encoding, Authorization, X-Api-Key, X-Request-Id, Idempotency-Key',
+    'Access-Control-Expose-Headers': 'X-Request-Id',
+    // The answer depends on Origin even when it carries no Allow-Origin, so a shared cache
+    // must not hand a response for a refused origin to an allowed one, or the other way round
+    'Vary': 'Origin',
+  };
+  
+  if (allowedOrigins.includes('*')) {
+    headers['Access-Control-Allow-Origin'] = requestOrigin || '*';
+  } else if (requestOrigin && allowedOrigins.includes(requestOrigin)) {
+    headers['Access-Control-Allow-Origin'] = requestOrigin;
+    // Startup rejects credentials combined with "*", so this is always an explicit origin
+    if (config.CORS_ALLOW_CREDENTIALS) {
+      headers['Access-Control-Allow-Credentials'] = 'true';
+    }
+  }
+  
+  return headers;
+}
+
+// Client address for rate limiting and logs. Forwarding headers are only honoured with
+// TRUST_PROXY: anyone can send them, so trusting them without a proxy that overwrites
+// them lets a client pick its own address and dodge the rate limit.
+function clientIp(req: Request, server: Server): string {
+  if (config.TRUST_PROXY) {
+    const forwarded = req.headers.get('x-forwarded-for')?.split(',')[0]?.trim()
+      || req.headers.get('x-real-ip')?.trim();
+    if (forwarded) return forwarded;
+  }
+  return server.requestIP(req)?.address || 'unknown';
+}
+
+// Per-request values resolved once in fetch and handed down to the handlers
+interface RequestContext {
+  requestId: string;
+  // Taken from the original request: server.requestIP() doesn't know the GET copy made for HEAD
+  clientIp: string;
+  // Decoded token claims (sub, scopes, ...) once AUTH_MODE=jwt authentication succeeds
+  claims?: JwtClaims;
+  // Set when the request was answered with a 408 while its handler was still running
+  timedOut?: boolean;
+}
+
+// True when the client explicitly asks for text/plain and not for JSON
+function acceptsPlainText(req: Request): boolean {
+  const accept = req.headers.get('accept') || '';
+  return accept.includes('text/plain') && !accept.includes('application/json');
+}
+
+// Routes are served both under /v1 and at the legacy unversioned paths, all below BASE_PATH.
+// Paths outside BASE_PATH have no route path at all and get 404.
+const API_VERSION_PREFIX = /^\/v1(?=\/|$)/;
+
+function routePath(url: URL): string | undefined {
+  let pathname = url.pathname;
+  if (config.BASE_PATH) {
+    if (pathname !== config.BASE_PATH && !pathname.startsWith(`${config.BASE_PATH}/`)) {
+      return undefined;
+    }
+    pathname = pathname.slice(config.BASE_PATH.length);
+  }
+  return pathname.replace(API_VERSION_PREFIX, '') || '/';
+}
+
+// Thrown by readJson when the request body can't be read; the message is safe to return to the client
+class RequestBodyError extends Error {
+  constructor(message: string, readonly status: 400 | 413 | 415) {
+    super(message);
+  }
+}
+
+// Read the body as text, inflating a Content-Encoding: gzip body first. Bun enforces
+// MAX_BODY_BYTES on the bytes received, so the inflated size is capped separately.
+async function readBodyText(req: Request): Promise<string> {
+  const encoding = (req.headers.get('content-encoding') || 'identity').trim().toLowerCase();
+  if (encoding === 'identity') {
+    return req.text();
+  }
+  
+  if (encoding !== 'gzip') {
+    throw new RequestBodyError(`unsupported content encoding '${encoding}', expected gzip`, 415);
+  }
+  
+  const compressed = new Uint8Array(await req.arrayBuffer());
+  try {
+    return gunzipSync(compressed, { maxOutputLength: config.MAX_BODY_BYTES }).toString('utf8');
+  } catch (error) {
+    if ((error as { code?: string }).code === 'ERR_BUFFER_TOO_LARGE') {
+      throw new RequestBodyError(`decompressed body exceeds ${config.MAX_BODY_BYTES} bytes`, 413);
+    }
+    throw new RequestBodyError('invalid gzip body', 400);
+  }
+}
+
+async function readJson<T = unknown>(req: Request): Promise<T> {
+  const body = await readBodyText(req);
+  try {
+    return JSON.parse(body) as T;
+  } catch (error) {
+    throw new RequestBodyError(`invalid json: ${error instanceof Error ? error.message : String(error)}`, 400);
+  }
+}
+
+// RFC 3339 date-time, e.g. 2025-01-31T12:00:00Z or 2025-01-31T12:00:00.5+02:00
+const RFC3339_PATTERN = /^\d{4}-\d{2}-\d{2}[Tt ]\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+-]\d{2}:\d{2})$/;
+
+// Thrown by the query parameter parsers below; handleWithTimeout turns it into a 400
+class InvalidQueryError extends Error {
+  constructor(detail: string) {
+    super(`invalid query parameter: ${detail}`);
+  }
+}
+
+// Read an optional RFC 3339 query parameter as epoch milliseconds
+function timestampParam(url: URL, name: string): number | undefined {
+  const raw = url.searchParams.get(name);
+  if (!raw) return undefined;
   
-  async fetch(req: Request) {
-    const url = new URL(req.url);
+  const value = RFC3339_PATTERN.test(raw) ? Date.parse(raw) : NaN;
+  if (Number.isNaN(value)) {
+    throw new InvalidQueryError(`${name} must be an RFC 3339 timestamp such as 2025-01-31T12:00:00Z, got '${raw}'`);
+  }
+  return value;
+}
+
+// Read an optional non-negative integer query parameter, rejecting values like 'abc' or '10x'
+function intParam(url: URL, name: string, min: number = 0): number | undefined {
+  const raw = url.searchParams.get(name);
+  if (raw === null || raw === '') return undefined;
+  
+  const value = Number(raw);
+  if (!/^\d+$/.test(raw) || value < min) {
+    throw new InvalidQueryError(`${name} must be an integer of at least ${min}, got '${raw}'`);
+  }
+  return value;
+}
+
+// Parse a single-range Range header (bytes=a-b, bytes=a- or bytes=-n) against a body of `size` bytes.
+// Returns undefined for headers we don't handle, which callers answer with the full body.
+function parseByteRange(header: string, size: number): { start: number; end: number } | 'unsatisfiable' | undefined {
+  const match = header.trim().match(/^bytes=(\d*)-(\d*)$/);
+  if (!match || (!match[1] && !match[2])) return undefined;
+  
+  let start: number;
+  let end: number;
+  if (!match[1]) {
+    // Suffix range: the last n bytes
+    const suffix = Number(match[2]);
+    if (suffix === 0) return 'unsatisfiable';
+    start = Math.max(0, size - suffix);
+    end = size - 1;
+  } else {
+    start = Number(match[1]);
+    end = match[2] ? Math.min(Number(match[2]), size - 1) : size - 1;
+  }
+  
+  if (start >= size || start > end) return 'unsatisfiable';
+  return { start, end };
+}
+
+// Routes switched off by configuration are left out of the index at / and otherwise
+// treated like unknown paths, so they answer 404 whatever the method
+function isRouteEnabled(route: RouteDefinition): boolean {
+  switch (route.path) {
+    case '/docs':
+    case '/openapi.json':
+      return docsEnabled;
+    case '/admin/shutdown':
+      return adminEnabled;
+    case '/debug/state':
+      return config.ENABLE_DEBUG;
+    default:
+      return true;
+  }
+}
+
+function matchEnabledRoute(pathname: string): RouteDefinition | undefined {
+  const route = matchRoute(pathname);
+  return route && isRouteEnabled(route) ? route : undefined;
+}
+
+// Route listing served at /, built once from the route table
+const rootEndpoints = routes
+  .filter(route => route.path !== '/' && isRouteEnabled(route))
+  .map(route => ({ path: `${config.BASE_PATH}${route.path}`, methods: route.methods }));
+
+// Largest number of events accepted by one POST /events/batch request
+const MAX_BATCH_EVENTS = 1000;
+
+// NORMALIZE_SOURCE_APP: fold 'Claude ' and 'claude' into one agent so stats and sessions
+// don't show near-duplicates. Runs before validation, so a blank name is still rejected.
+function normalizeSourceApp(event: HookEvent | null | undefined): void {
+  if (config.NORMALIZE_SOURCE_APP && event && typeof event.source_app === 'string') {
+    event.source_app = event.source_app.trim().toLowerCase();
+  }
+}
+
+function isCompleteEvent(event: HookEvent | null | undefined): boolean {
+  return Boolean(event && event.source_app && event.session_id && event.hook_event_type && event.payload);
+}
+
+// STRICT_EVENTS: only hook types from KNOWN_EVENT_TYPES are stored
+const knownEventTypes = new Set(config.KNOWN_EVENT_TYPES);
+
+function isKnownEventType(event: HookEvent): boolean {
+  return !config.STRICT_EVENTS || knownEventTypes.has(event.hook_event_type);
+}
+
+// Answer for a request that ran past REQUEST_TIMEOUT_MS
+function timedOutResponse(req: Request): Response {
+  return new Response(JSON.stringify({ error: 'Request timed out' }), {
+    status: 408,
+    headers: { ...corsHeaders(req), 'Content-Type': 'application/json' }
+  });
+}
+
+// Route a single HTTP request. `signal` aborts once the request has timed out: the client
+// already has its 408 and may be retrying, so handlers check it before writing anything.
+async function handleRequest(req: Request, server: Server, ctx: RequestContext, signal: AbortSignal): Promise<Response | undefined> {
+  const url = new URL(req.url);
+  const pathname = routePath(url);
+  const headers = corsHeaders(req);
+  
+  if (pathname === undefined) {
+    return new Response(JSON.stringify({ error: 'route not found', path: url.pathname }), {
+      status: 404,
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // Handle preflight
+  if (req.method === 'OPTIONS') {
+    const preflightHeaders = config.CORS_MAX_AGE_SECONDS !== undefined
+      ? { ...headers, 'Access-Control-Max-Age': String(config.CORS_MAX_AGE_SECONDS) }
+      : headers;
+    return new Response(null, { headers: preflightHeaders });
+  }
+  
+  // Check the credentials AUTH_MODE asks for; with AUTH_MODE=jwt the handlers below read the
+  // token's claims (sub, scopes, ...) from auth.claims
+  const auth = authenticate(req, url, pathname);
+  if (!auth.ok) {
+    return new Response(JSON.stringify({ error: auth.error }), {
+      status: auth.status,
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  ctx.claims = auth.claims;
+  
+  // GET /health, /health/live - Liveness: the process is up and serving requests
+  if ((pathname === '/health' || pathname === '/health/live') && req.method === 'GET') {
+    // Uptime monitors that ask for text/plain get a one-line body instead of JSON
+    if (acceptsPlainText(req)) {
+      return new Response('status: healthy', {
+        headers: { ...headers, 'Content-Type': 'text/plain; charset=utf-8' }
+      });
+    }
     
-    // Handle CORS
-    const allowedOrigins = Array.isArray(config.CORS_ORIGINS) ? config.CORS_ORIGINS : [config.CORS_ORIGINS];
-    const requestOrigin = req.headers.get('origin');
-    const corsOrigin = allowedOrigins.includes('*') || allowedOrigins.includes(requestOrigin || '') ? (requestOrigin || '*') : 'null';
+    return new Response(JSON.stringify({
+      status: 'healthy',
+      timestamp: new Date().toISOString(),
+      uptime_seconds: Math.floor((Date.now() - startedAt) / 1000),
+      version: packageJson.version
+    }), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /version - Which build is running
+  if (pathname === '/version' && req.method === 'GET') {
+    return new Response(JSON.stringify(buildInfo), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /health/ready - Readiness: every dependency check passes and the server isn't shutting down;
+  // 'degraded' (still 200) when the event store is full and evicting
+  if (pathname === '/health/ready' && req.method === 'GET') {
+    const database = pingDatabase();
+    const checks: ReadinessCheck[] = [
+      database.ok
+        ? { name: 'database', status: 'ok' }
+        : { name: 'database', status: 'failing', error: database.error }
+    ];
+    const isReady = !shuttingDown && checks.every(check => check.status === 'ok');
     
-    const headers = {
-      'Access-Control-Allow-Origin': corsOrigin,
-      'Access-Control-Allow-Methods': 'GET, POST, PUT, DELETE, OPTIONS',
-      'Access-Control-Allow-Headers': 'Content-Type',
-    };
+    // A full store that has started evicting is still serving, but is silently dropping history
+    const buffer = database.ok ? getEventCount() : undefined;
+    const isSaturated = buffer !== undefined && buffer.capacity > 0 && buffer.count >= buffer.capacity && buffer.evicted > 0;
     
-    // Handle preflight
-    if (req.method === 'OPTIONS') {
-      return new Response(null, { headers });
+    return new Response(JSON.stringify({
+      status: !isReady ? 'not_ready' : isSaturated ? 'degraded' : 'ready',
+      timestamp: new Date().toISOString(),
+      checks,
+      event_buffer_used: buffer?.count,
+      event_buffer_capacity: buffer?.capacity
+    }), {
+      status: isReady ? 200 : 503,
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /metrics - Prometheus metrics
+  if (pathname === '/metrics' && req.method === 'GET') {
+    // The client sets are the source of truth, so the gauge can't drift however a connection ends
+    return new Response(renderMetrics({ websocket: wsClients.size, sse: sseClients.size }), {
+      headers: { ...headers, 'Content-Type': 'text/plain; version=0.0.4; charset=utf-8' }
+    });
+  }
+  
+  // GET /openapi.json - OpenAPI description of this API
+  if (pathname === '/openapi.json' && req.method === 'GET' && docsEnabled) {
+    return new Response(JSON.stringify(buildOpenApiSpec()), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /docs - Interactive Swagger UI for the OpenAPI spec
+  if (pathname === '/docs' && req.method === 'GET' && docsEnabled) {
+    return new Response(SWAGGER_UI_HTML, {
+      headers: { ...headers, 'Content-Type': 'text/html; charset=utf-8' }
+    });
+  }
+  
+  // POST /admin/shutdown - Trigger the same graceful shutdown as SIGTERM
+  if (pathname === '/admin/shutdown' && req.method === 'POST' && adminEnabled) {
+    logger.warn('Shutdown requested via admin endpoint', { client_ip: ctx.clientIp, subject: auth.claims?.sub });
+    // Let this response go out before the server stops accepting connections
+    setTimeout(() => shutdown('POST /admin/shutdown'), 0);
+    return new Response(JSON.stringify({ status: 'shutting_down' }), {
+      status: 202,
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /debug/state - Internal counters in one place, for support requests (ENABLE_DEBUG only)
+  if (pathname === '/debug/state' && req.method === 'GET' && config.ENABLE_DEBUG) {
+    return new Response(JSON.stringify({
+      events: getEventCount(),
+      subscribers: { websocket: wsClients.size, sse: sseClients.size },
+      uptime_seconds: Math.floor((Date.now() - startedAt) / 1000)
+    }), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // POST /events - Receive new events
+  if (pathname === '/events' && req.method === 'POST') {
+    const limit = takeToken(ctx.clientIp);
+    if (!limit.allowed) {
+      return new Response(JSON.stringify({ error: 'Too many requests' }), {
+        status: 429,
+        headers: { ...headers, 'Content-Type': 'application/json', 'Retry-After': String(limit.retryAfterSeconds) }
+      });
     }
     
-    // POST /events - Receive new events
-    if (url.pathname === '/events' && req.method === 'POST') {
-      try {
-        const event = await req.json() as HookEvent;
-        
-        // Validate required fields
-        if (!event.source_app || !event.session_id || !event.hook_event_type || !event.payload) {
-          return new Response(JSON.stringify({ error: 'Missing required fields' }), {
-            status: 400,
-            headers: { ...headers, 'Content-Type': 'application/json' }
-          });
-        }
-        
-        // Insert event into database
-        const savedEvent = insertEvent(event);
-        
-        // Broadcast to all WebSocket clients
-        const message = JSON.stringify({ type: 'event', data: savedEvent });
-        wsClients.forEach(client => {
-          try {
-            client.send(message);
-          } catch (err) {
-            // Client disconnected, remove from set
-            wsClients.delete(client);
-          }
+    try {
+      const event = await readJson<HookEvent>(req);
+      normalizeSourceApp(event);
+      
+      // Validate required fields
+      if (!isCompleteEvent(event)) {
+        return new Response(JSON.stringify({ error: 'Missing required fields' }), {
+          status: 400,
+          headers: { ...headers, 'Content-Type': 'application/json' }
         });
-        
-        return new Response(JSON.stringify(savedEvent), {
+      }
+      
+      if (!isKnownEventType(event)) {
+        return new Response(JSON.stringify({ error: `Unknown hook event type '${event.hook_event_type}'` }), {
+          status: 422,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
-      } catch (error) {
-        console.error('Error processing event:', error);
-        return new Response(JSON.stringify({ error: 'Invalid request' }), {
-          status: 400,
+      }
+      
+      // A retried request with a known Idempotency-Key gets the original event back
+      const idempotencyKey = req.headers.get('idempotency-key')?.trim() || undefined;
+      if (idempotencyKey) {
+        const existingId = lookupIdempotencyKey(idempotencyKey);
+        const existing = existingId !== undefined ? getEventById(existingId) : undefined;
+        if (existing) {
+          return new Response(JSON.stringify(existing), {
+            headers: { ...headers, 'Content-Type': 'application/json', 'Idempotent-Replayed': 'true' }
+          });
+        }
+      }
+      
+      if (signal.aborted) {
+        return timedOutResponse(req);
+      }
+      
+      // Insert event into database
+      const savedEvent = eventSink.record(event);
+      recordEventIngested(savedEvent.source_app, savedEvent.hook_event_type);
+      if (idempotencyKey && savedEvent.id !== undefined) {
+        rememberIdempotencyKey(idempotencyKey, savedEvent.id);
+      }
+      
+      // Broadcast to all WebSocket clients
+      broadcastEvent(savedEvent);
+      forwardEvent(savedEvent);
+      
+      return new Response(JSON.stringify(savedEvent), {
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    } catch (error) {
+      if (error instanceof RequestBodyError) {
+        return new Response(JSON.stringify({ error: error.message }), {
+          status: error.status,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
       }
-    }
-    
-    // GET /events/filter-options - Get available filter options
-    if (url.pathname === '/events/filter-options' && req.method === 'GET') {
-      const options = getFilterOptions();
-      return new Response(JSON.stringify(options), {
+      logger.error('Error processing event', { error });
+      return new Response(JSON.stringify({ error: 'Invalid request' }), {
+        status: 400,
         headers: { ...headers, 'Content-Type': 'application/json' }
       });
     }
-    
-    // GET /events/recent - Get recent events
-    if (url.pathname === '/events/recent' && req.method === 'GET') {
-      const limit = parseInt(url.searchParams.get('limit') || '100');
-      const events = getRecentEvents(limit);
-      return new Response(JSON.stringify(events), {
-        headers: { ...headers, 'Content-Type': 'application/json' }
+  }
+  
+  // POST /events/batch - Receive several events in one request
+  if (pathname === '/events/batch' && req.method === 'POST') {
+    const limit = takeToken(ctx.clientIp);
+    if (!limit.allowed) {
+      return new Response(JSON.stringify({ error: 'Too many requests' }), {
+        status: 429,
+        headers: { ...headers, 'Content-Type': 'application/json', 'Retry-After': String(limit.retryAfterSeconds) }
       });
     }
     
-    // Theme API endpoints
-    
-    // POST /api/themes - Create a new theme
-    if (url.pathname === '/api/themes' && req.method === 'POST') {
-      try {
-        const themeData = await req.json();
-        const result = await createTheme(themeData);
-        
-        const status = result.success ? 201 : 400;
-        return new Response(JSON.stringify(result), {
-          status,
+    try {
+      const events = await readJson<HookEvent[]>(req);
+      
+      if (!Array.isArray(events)) {
+        return new Response(JSON.stringify({ error: 'Expected a JSON array of events' }), {
+          status: 400,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
-      } catch (error) {
-        console.error('Error creating theme:', error);
-        return new Response(JSON.stringify({ 
-          success: false, 
-          error: 'Invalid request body' 
-        }), {
-          status: 400,
+      }
+      
+      if (events.length > MAX_BATCH_EVENTS) {
+        return new Response(JSON.stringify({ error: `Batch too large: ${events.length} events, at most ${MAX_BATCH_EVENTS} allowed` }), {
+          status: 422,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
       }
-    }
-    
-    // GET /api/themes - Search themes
-    if (url.pathname === '/api/themes' && req.method === 'GET') {
-      const query = {
-        query: url.searchParams.get('query') || undefined,
-        isPublic: url.searchParams.get('isPublic') ? url.searchParams.get('isPublic') === 'true' : undefined,
-        authorId: url.searchParams.get('authorId') || undefined,
-        sortBy: url.searchParams.get('sortBy') as any || undefined,
-        sortOrder: url.searchParams.get('sortOrder') as any || undefined,
-        limit: url.searchParams.get('limit') ? parseInt(url.searchParams.get('limit')!) : undefined,
-        offset: url.searchParams.get('offset') ? parseInt(url.searchParams.get('offset')!) : undefined,
-      };
       
-      const result = await searchThemes(query);
-      return new Response(JSON.stringify(result), {
-        headers: { ...headers, 'Content-Type': 'application/json' }
-      });
-    }
-    
-    // GET /api/themes/:id - Get a specific theme
-    if (url.pathname.startsWith('/api/themes/') && req.method === 'GET') {
-      const id = url.pathname.split('/')[3];
-      if (!id) {
-        return new Response(JSON.stringify({ 
-          success: false, 
-          error: 'Theme ID is required' 
-        }), {
+      events.forEach(normalizeSourceApp);
+      
+      // Reject the whole batch rather than storing part of it
+      const invalidIndex = events.findIndex(event => !isCompleteEvent(event));
+      if (invalidIndex !== -1) {
+        return new Response(JSON.stringify({ error: `Missing required fields in event at index ${invalidIndex}` }), {
           status: 400,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
       }
       
-      const result = await getThemeById(id);
-      const status = result.success ? 200 : 404;
-      return new Response(JSON.stringify(result), {
-        status,
-        headers: { ...headers, 'Content-Type': 'application/json' }
-      });
-    }
-    
-    // PUT /api/themes/:id - Update a theme
-    if (url.pathname.startsWith('/api/themes/') && req.method === 'PUT') {
-      const id = url.pathname.split('/')[3];
-      if (!id) {
-        return new Response(JSON.stringify({ 
-          success: false, 
-          error: 'Theme ID is required' 
-        }), {
-          status: 400,
+      const unknownIndex = events.findIndex(event => !isKnownEventType(event));
+      if (unknownIndex !== -1) {
+        return new Response(JSON.stringify({ error: `Unknown hook event type '${events[unknownIndex]?.hook_event_type}' in event at index ${unknownIndex}` }), {
+          status: 422,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
       }
       
-      try {
-        const updates = await req.json();
-        const result = await updateThemeById(id, updates);
-        
-        const status = result.success ? 200 : 400;
-        return new Response(JSON.stringify(result), {
-          status,
+      // One oversized entry gets pointed out by index instead of failing the batch with a blanket 413
+      const oversizedIndex = events.findIndex(event => Buffer.byteLength(JSON.stringify(event.payload)) > config.MAX_EVENT_PAYLOAD_BYTES);
+      if (oversizedIndex !== -1) {
+        return new Response(JSON.stringify({ error: `Payload of event at index ${oversizedIndex} exceeds ${config.MAX_EVENT_PAYLOAD_BYTES} bytes` }), {
+          status: 422,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
-      } catch (error) {
-        console.error('Error updating theme:', error);
-        return new Response(JSON.stringify({ 
-          success: false, 
-          error: 'Invalid request body' 
-        }), {
-          status: 400,
+      }
+      
+      if (signal.aborted) {
+        return timedOutResponse(req);
+      }
+      
+      const savedEvents = eventSink.recordBatch(events);
+      for (const savedEvent of savedEvents) {
+        recordEventIngested(savedEvent.source_app, savedEvent.hook_event_type);
+        broadcastEvent(savedEvent);
+        forwardEvent(savedEvent);
+      }
+      
+      return new Response(JSON.stringify({ accepted: savedEvents.length }), {
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    } catch (error) {
+      if (error instanceof RequestBodyError) {
+        return new Response(JSON.stringify({ error: error.message }), {
+          status: error.status,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
       }
+      logger.error('Error processing event batch', { error });
+      return new Response(JSON.stringify({ error: 'Invalid request' }), {
+        status: 400,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
     }
+  }
+  
+  // PATCH /events/:id - Annotate a stored event with tags and/or a note
+  const annotateMatch = pathname.match(/^\/events\/(\d+)$/);
+  if (annotateMatch && req.method === 'PATCH') {
+    const id = Number(annotateMatch[1]);
     
-    // DELETE /api/themes/:id - Delete a theme
-    if (url.pathname.startsWith('/api/themes/') && req.method === 'DELETE') {
-      const id = url.pathname.split('/')[3];
-      if (!id) {
-        return new Response(JSON.stringify({ 
-          success: false, 
-          error: 'Theme ID is required' 
-        }), {
+    try {
+      const annotation = await readJson<EventAnnotation>(req);
+      const tagsValid = annotation?.tags === undefined
+        || (Array.isArray(annotation.tags) && annotation.tags.every(tag => typeof tag === 'string'));
+      const noteValid = annotation?.note === undefined || annotation.note === null || typeof annotation.note === 'string';
+      
+      if (!annotation || typeof annotation !== 'object' || !tagsValid || !noteValid) {
+        return new Response(JSON.stringify({ error: 'Expected {tags?: string[], note?: string | null}' }), {
           status: 400,
           headers: { ...headers, 'Content-Type': 'application/json' }
         });
       }
       
-      const authorId = url.searchParams.get('authorId');
-      const result = await deleteThemeById(id, authorId || undefined);
+      if (signal.aborted) {
+        return timedOutResponse(req);
+      }
       
-      const status = result.success ? 200 : (result.error?.includes('not found') ? 404 : 403);
-      return new Response(JSON.stringify(result), {
-        status,
+      const updated = eventSink.annotate(id, { tags: annotation.tags, note: annotation.note });
+      if (!updated) {
+        return new Response(JSON.stringify({ error: 'event not found', id }), {
+          status: 404,
+          headers: { ...headers, 'Content-Type': 'application/json' }
+        });
+      }
+      
+      return new Response(JSON.stringify(updated), {
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    } catch (error) {
+      if (error instanceof RequestBodyError) {
+        return new Response(JSON.stringify({ error: error.message }), {
+          status: error.status,
+          headers: { ...headers, 'Content-Type': 'application/json' }
+        });
+      }
+      throw error;
+    }
+  }
+  
+  // GET /events - Query events, newest first, filtered by source_app/session_id/hook_event_type
+  // and the [since, until) time window
+  if (pathname === '/events' && req.method === 'GET') {
+    const events = eventSink.query({
+      source_app: url.searchParams.get('source_app') || undefined,
+      session_id: url.searchParams.get('session_id') || undefined,
+      hook_event_type: url.searchParams.get('hook_event_type') || undefined,
+      since: timestampParam(url, 'since'),
+      until: timestampParam(url, 'until'),
+      limit: intParam(url, 'limit', 1) ?? 100
+    });
+    return new Response(JSON.stringify(events), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /events/search - Substring search across event payloads and types, newest first
+  if (pathname === '/events/search' && req.method === 'GET') {
+    const q = url.searchParams.get('q')?.trim();
+    if (!q) {
+      return new Response(JSON.stringify({ error: 'invalid query parameter: q must not be empty' }), {
+        status: 400,
         headers: { ...headers, 'Content-Type': 'application/json' }
       });
     }
     
-    // GET /api/themes/:id/export - Export a theme
-    if (url.pathname.match(/^\/api\/themes\/[^\/]+\/export$/) && req.method === 'GET') {
-      const id = url.pathname.split('/')[3];
+    const events = eventSink.query({ search: q, limit: intParam(url, 'limit', 1) ?? 100 });
+    return new Response(JSON.stringify(events), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /events/count - Stored event count against the retention cap
+  if (pathname === '/events/count' && req.method === 'GET') {
+    return new Response(JSON.stringify(getEventCount()), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /events/stats - Aggregate counts by event type and source app
+  if (pathname === '/events/stats' && req.method === 'GET') {
+    return new Response(JSON.stringify(eventSink.stats()), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /events/export - Stream stored events as newline-delimited JSON, oldest first
+  if (pathname === '/events/export' && req.method === 'GET') {
+    const filter = { session_id: url.searchParams.get('session_id') || undefined };
+    const etag = `"${exportVersion(filter)}"`;
+    const exportHeaders = {
+      ...headers,
+      'Content-Type': 'application/x-ndjson',
+      'Content-Disposition': 'attachment; filename="events.ndjson"',
+      'Accept-Ranges': 'bytes',
+      'ETag': etag
+    };
+    
+    // Eviction and PATCH shift the bytes of an export, so a client resuming a download sends
+    // the ETag it started with: If-Match fails outright, If-Range falls back to the full body
+    const ifMatch = req.headers.get('if-match');
+    if (ifMatch && !ifMatch.split(',').some(tag => tag.trim() === etag || tag.trim() === '*')) {
+      return new Response(JSON.stringify({ error: 'export changed since the ETag was issued' }), {
+        status: 412,
+        headers: { ...headers, 'Content-Type': 'application/json', 'ETag': etag }
+      });
+    }
+    
+    const rows = iterateEvents(filter);
+    const encoder = new TextEncoder();
+    
+    // Resuming a download needs the total length up front, so a ranged request renders the
+    // whole export before slicing it
+    const ifRange = req.headers.get('if-range');
+    const rangeHeader = !ifRange || ifRange.trim() === etag ? req.headers.get('range') : null;
+    if (rangeHeader) {
+      const chunks: string[] = [];
+      for (const event of rows) {
+        chunks.push(JSON.stringify(event) + '\n');
+      }
+      const body = encoder.encode(chunks.join(''));
+      const range = parseByteRange(rangeHeader, body.byteLength);
       
-      if (!id) {
-        return new Response(JSON.stringify({ 
-          success: false, 
-          error: 'Theme ID is required' 
-        }), {
-          status: 400,
-          headers: { ...headers, 'Content-Type': 'application/json' }
+      if (range === 'unsatisfiable') {
+        return new Response(null, {
+          status: 416,
+          headers: { ...exportHeaders, 'Content-Range': `bytes */${body.byteLength}` }
         });
       }
       
-      const result = await exportThemeById(id);
-      if (!result.success) {
-        const status = result.error?.includes('not found') ? 404 : 400;
-        return new Response(JSON.stringify(result), {
-          status,
-          headers: { ...headers, 'Content-Type': 'application/json' }
+      if (range) {
+        return new Response(body.slice(range.start, range.end + 1), {
+          status: 206,
+          headers: { ...exportHeaders, 'Content-Range': `bytes ${range.start}-${range.end}/${body.byteLength}` }
         });
       }
       
-      return new Response(JSON.stringify(result.data), {
-        headers: { 
-          ...headers, 
-          'Content-Type': 'application/json',
-          'Content-Disposition': `attachment; filename="${result.data.theme.name}.json"`
+      // A Range we don't support (e.g. several ranges) is ignored and the full body sent
+      return new Response(body, { headers: exportHeaders });
+    }
+    
+    // Pull one row at a time so a large export never sits in memory all at once
+    const stream = new ReadableStream<Uint8Array>({
+      pull(controller) {
+        const next = rows.next();
+        if (next.done) {
+          controller.close();
+        } else {
+          controller.enqueue(encoder.encode(JSON.stringify(next.value) + '\n'));
         }
+      },
+      cancel() {
+        rows.return(undefined);
+      }
+    });
+    
+    return new Response(stream, { headers: exportHeaders });
+  }
+  
+  // GET /events/stream - Server-Sent Events alternative to the /stream WebSocket
+  if (pathname === '/events/stream' && req.method === 'GET') {
+    // The stream stays open indefinitely, so lift Bun's idle timeout for this request
+    server.timeout(req, 0);
+    
+    let client: SseClient;
+    const stream = new ReadableStream<Uint8Array>({
+      start(controller) {
+        client = {
+          controller,
+          keepAlive: setInterval(() => sseSend(client, ': keep-alive\n\n'), SSE_KEEPALIVE_MS)
+        };
+        sseClients.add(client);
+        sseSend(client, ': connected\n\n');
+      },
+      cancel() {
+        removeSseClient(client);
+      }
+    }, new ByteLengthQueuingStrategy({ highWaterMark: MAX_SUBSCRIBER_BUFFER_BYTES }));
+    
+    return new Response(stream, {
+      headers: {
+        ...headers,
+        'Content-Type': 'text/event-stream',
+        'Cache-Control': 'no-cache',
+        'Connection': 'keep-alive'
+      }
+    });
+  }
+  
+  // GET /events/filter-options - Get available filter options
+  if (pathname === '/events/filter-options' && req.method === 'GET') {
+    const options = getFilterOptions();
+    return new Response(JSON.stringify(options), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /events/recent - Get recent events
+  if (pathname === '/events/recent' && req.method === 'GET') {
+    const limit = intParam(url, 'limit', 1) ?? 100;
+    const events = getRecentEvents(limit);
+    return new Response(JSON.stringify(events), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /sessions - Recently active sessions with event counts
+  if (pathname === '/sessions' && req.method === 'GET') {
+    const limit = intParam(url, 'limit', 1) ?? 50;
+    return new Response(JSON.stringify(getSessions(limit)), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /sessions/:session_id/timeline - A session's events in order, with time since the previous event
+  const timelineMatch = pathname.match(/^\/sessions\/([^\/]+)\/timeline$/);
+  if (timelineMatch && req.method === 'GET') {
+    let sessionId: string;
+    try {
+      sessionId = decodeURIComponent(timelineMatch[1]!);
+    } catch (error) {
+      // Malformed percent-encoding such as %E0
+      return new Response(JSON.stringify({ error: 'invalid session id: malformed percent-encoding' }), {
+        status: 400,
+        headers: { ...headers, 'Content-Type': 'application/json' }
       });
     }
+    const events = getSessionEvents(sessionId);
     
-    // POST /api/themes/import - Import a theme
-    if (url.pathname === '/api/themes/import' && req.method === 'POST') {
-      try {
-        const importData = await req.json();
-        const authorId = url.searchParams.get('authorId');
-        
-        const result = await importTheme(importData, authorId || undefined);
-        
-        const status = result.success ? 201 : 400;
-        return new Response(JSON.stringify(result), {
-          status,
-          headers: { ...headers, 'Content-Type': 'application/json' }
-        });
-      } catch (error) {
-        console.error('Error importing theme:', error);
-        return new Response(JSON.stringify({ 
-          success: false, 
-          error: 'Invalid import data' 
-        }), {
-          status: 400,
-          headers: { ...headers, 'Content-Type': 'application/json' }
-        });
+    if (events.length === 0) {
+      return new Response(JSON.stringify({ error: 'session not found', session_id: sessionId }), {
+        status: 404,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    }
+    
+    const timeline: TimelineEntry[] = events.map((event, i) => ({
+      ...event,
+      delta_ms: i === 0 ? 0 : (event.timestamp ?? 0) - (events[i - 1]!.timestamp ?? 0)
+    }));
+    
+    return new Response(JSON.stringify({ session_id: sessionId, events: timeline }), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // Theme API endpoints
+  
+  // POST /api/themes - Create a new theme
+  if (pathname === '/api/themes' && req.method === 'POST') {
+    try {
+      const themeData = await readJson(req);
+      if (signal.aborted) {
+        return timedOutResponse(req);
+      }
+      const result = await createTheme(themeData);
+      
+      const status = result.success ? 201 : 400;
+      return new Response(JSON.stringify(result), {
+        status,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    } catch (error) {
+      logger.error('Error creating theme', { error });
+      return new Response(JSON.stringify({ 
+        success: false, 
+        error: error instanceof RequestBodyError ? error.message : 'Invalid request body'
+      }), {
+        status: error instanceof RequestBodyError ? error.status : 400,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    }
+  }
+  
+  // GET /api/themes - Search themes
+  if (pathname === '/api/themes' && req.method === 'GET') {
+    const query = {
+      query: url.searchParams.get('query') || undefined,
+      isPublic: url.searchParams.get('isPublic') ? url.searchParams.get('isPublic') === 'true' : undefined,
+      authorId: url.searchParams.get('authorId') || undefined,
+      sortBy: url.searchParams.get('sortBy') as any || undefined,
+      sortOrder: url.searchParams.get('sortOrder') as any || undefined,
+      limit: intParam(url, 'limit', 1),
+      offset: intParam(url, 'offset'),
+    };
+    
+    const result = await searchThemes(query);
+    return new Response(JSON.stringify(result), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // /api/themes/:id takes exactly one path segment, and 'import' and 'stats' are routes of
+  // their own: matching on the prefix alone would treat /api/themes/x/export as theme x
+  const themeId = pathname.match(/^\/api\/themes\/(?!(?:import|stats)$)([^\/]+)$/)?.[1];
+  
+  // GET /api/themes/:id - Get a specific theme
+  if (themeId && req.method === 'GET') {
+    const result = await getThemeById(themeId);
+    const status = result.success ? 200 : 404;
+    return new Response(JSON.stringify(result), {
+      status,
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // PUT /api/themes/:id - Update a theme
+  if (themeId && req.method === 'PUT') {
+    try {
+      const updates = await readJson(req);
+      if (signal.aborted) {
+        return timedOutResponse(req);
       }
+      const result = await updateThemeById(themeId, updates);
+      
+      const status = result.success ? 200 : 400;
+      return new Response(JSON.stringify(result), {
+        status,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    } catch (error) {
+      logger.error('Error updating theme', { error });
+      return new Response(JSON.stringify({ 
+        success: false, 
+        error: error instanceof RequestBodyError ? error.message : 'Invalid request body'
+      }), {
+        status: error instanceof RequestBodyError ? error.status : 400,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
     }
+  }
+  
+  // DELETE /api/themes/:id - Delete a theme
+  if (themeId && req.method === 'DELETE') {
+    const authorId = url.searchParams.get('authorId');
+    const result = await deleteThemeById(themeId, authorId || undefined);
+    
+    const status = result.success ? 200 : (result.error?.includes('not found') ? 404 : 403);
+    return new Response(JSON.stringify(result), {
+      status,
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // GET /api/themes/:id/export - Export a theme
+  if (pathname.match(/^\/api\/themes\/[^\/]+\/export$/) && req.method === 'GET') {
+    const id = pathname.split('/')[3];
     
-    // GET /api/themes/stats - Get theme statistics
-    if (url.pathname === '/api/themes/stats' && req.method === 'GET') {
-      const result = await getThemeStats();
+    if (!id) {
+      return new Response(JSON.stringify({ 
+        success: false, 
+        error: 'Theme ID is required' 
+      }), {
+        status: 400,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    }
+    
+    const result = await exportThemeById(id);
+    if (!result.success) {
+      const status = result.error?.includes('not found') ? 404 : 400;
       return new Response(JSON.stringify(result), {
+        status,
         headers: { ...headers, 'Content-Type': 'application/json' }
       });
     }
     
-    // WebSocket upgrade
-    if (url.pathname === '/stream') {
-      const success = server.upgrade(req);
-      if (success) {
-        return undefined;
+    return new Response(JSON.stringify(result.data), {
+      headers: { 
+        ...headers, 
+        'Content-Type': 'application/json',
+        'Content-Disposition': `attachment; filename="${result.data.theme.name}.json"`
+      }
+    });
+  }
+  
+  // POST /api/themes/import - Import a theme
+  if (pathname === '/api/themes/import' && req.method === 'POST') {
+    try {
+      const importData = await readJson(req);
+      if (signal.aborted) {
+        return timedOutResponse(req);
       }
+      const authorId = url.searchParams.get('authorId');
+      
+      const result = await importTheme(importData, authorId || undefined);
+      
+      const status = result.success ? 201 : 400;
+      return new Response(JSON.stringify(result), {
+        status,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    } catch (error) {
+      logger.error('Error importing theme', { error });
+      return new Response(JSON.stringify({ 
+        success: false, 
+        error: error instanceof RequestBodyError ? error.message : 'Invalid import data'
+      }), {
+        status: error instanceof RequestBodyError ? error.status : 400,
+        headers: { ...headers, 'Content-Type': 'application/json' }
+      });
+    }
+  }
+  
+  // GET /api/themes/stats - Get theme statistics
+  if (pathname === '/api/themes/stats' && req.method === 'GET') {
+    const result = await getThemeStats();
+    return new Response(JSON.stringify(result), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // WebSocket upgrade (GET only; HEAD and other methods fall through to the 405 below)
+  if (pathname === '/stream' && req.method === 'GET') {
+    const data: StreamSocketData = { sinceId: intParam(url, 'since_id') };
+    const success = server.upgrade(req, { data });
+    if (success) {
+      return undefined;
+    }
+    return new Response(JSON.stringify({ error: 'WebSocket upgrade failed' }), {
+      status: 400,
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // Root response - a quick index of the API
+  if (pathname === '/') {
+    return new Response(JSON.stringify({
+      name: 'Multi-Agent Observability Server',
+      version: packageJson.version,
+      endpoints: rootEndpoints
+    }), {
+      headers: { ...headers, 'Content-Type': 'application/json' }
+    });
+  }
+  
+  // Known path, wrong method - 405 with the methods the route does support
+  const route = matchEnabledRoute(pathname);
+  if (route && !route.methods.includes(req.method)) {
+    const allowed = route.methods.includes('GET') && !route.stream ? [...route.methods, 'HEAD'] : route.methods;
+    return new Response(JSON.stringify({
+      error: 'method not allowed',
+      method: req.method,
+      allowed
+    }), {
+      status: 405,
+      headers: { ...headers, 'Content-Type': 'application/json', 'Allow': allowed.join(', ') }
+    });
+  }
+  
+  // Fallback - unmatched route
+  return new Response(JSON.stringify({ error: 'route not found', path: url.pathname }), {
+    status: 404,
+    headers: { ...headers, 'Content-Type': 'application/json' }
+  });
+}
+
+// Race the handler against REQUEST_TIMEOUT_MS so a stuck request cannot hold a connection forever,
+// and turn anything it throws into a 400 (bad query parameters) or a 500. A promise can't be
+// cancelled, so on timeout the handler's signal is aborted and it stops short of any write.
+const TIMED_OUT = Symbol('timed-out');
+
+// `onSettled` runs when the handler itself finishes, which after a timeout is later than the 408
+async function handleWithTimeout(req: Request, server: Server, ctx: RequestContext, onSettled?: () => void): Promise<Response | undefined> {
+  const controller = new AbortController();
+  let timer: ReturnType<typeof setTimeout> | undefined;
+  const timeout = new Promise<typeof TIMED_OUT>((resolve) => {
+    timer = setTimeout(() => {
+      controller.abort();
+      resolve(TIMED_OUT);
+    }, config.REQUEST_TIMEOUT_MS);
+  });
+  
+  try {
+    const handling = handleRequest(req, server, ctx, controller.signal);
+    if (onSettled) {
+      handling.then(onSettled, onSettled);
+    }
+    const result = await Promise.race([handling, timeout]);
+    if (result === TIMED_OUT) {
+      ctx.timedOut = true;
+      logger.warn('Request timed out', {
+        request_id: ctx.requestId,
+        method: req.method,
+        path: routePath(new URL(req.url)),
+        timeout_ms: config.REQUEST_TIMEOUT_MS
+      });
+      return timedOutResponse(req);
+    }
+    return result;
+  } catch (error) {
+    if (error instanceof InvalidQueryError) {
+      return new Response(JSON.stringify({ error: error.message }), {
+        status: 400,
+        headers: { ...corsHeaders(req), 'Content-Type': 'application/json' }
+      });
     }
     
-    // Default response
-    return new Response('Multi-Agent Observability Server', {
-      headers: { ...headers, 'Content-Type': 'text/plain' }
+    // A bug in one handler must not drop the connection without a response
+    logger.error('Unhandled error in request handler', {
+      error,
+      request_id: ctx.requestId,
+      method: req.method,
+      path: routePath(new URL(req.url))
+    });
+    return new Response(JSON.stringify({ error: 'internal server error' }), {
+      status: 500,
+      headers: { ...corsHeaders(req), 'Content-Type': 'application/json' }
+    });
+  } finally {
+    clearTimeout(timer);
+  }
+}
+
+// MAX_CONNECTIONS: cap on requests being handled at once; the excess is shed with 503.
+// Streams only count while their handler runs, and health probes are never shed so an
+// orchestrator doesn't restart a server that is merely busy.
+let inFlightRequests = 0;
+const CONCURRENCY_EXEMPT_ROUTES = new Set(['/health', '/health/live', '/health/ready']);
+
+async function handleWithConcurrencyLimit(req: Request, server: Server, ctx: RequestContext, route: RouteDefinition | undefined): Promise<Response | undefined> {
+  const limit = config.MAX_CONNECTIONS;
+  if (limit === undefined || (route && CONCURRENCY_EXEMPT_ROUTES.has(route.path))) {
+    return handleWithTimeout(req, server, ctx);
+  }
+  
+  if (inFlightRequests >= limit) {
+    logger.warn('Rejecting request, concurrency limit reached', { request_id: ctx.requestId, limit });
+    return new Response(JSON.stringify({ error: 'Server busy' }), {
+      status: 503,
+      headers: { ...corsHeaders(req), 'Content-Type': 'application/json', 'Retry-After': '1' }
     });
-  },
+  }
+  
+  // The slot is held until the handler settles: a timed-out handler that is still running
+  // keeps using the server even though its client has been answered
+  inFlightRequests++;
+  return handleWithTimeout(req, server, ctx, () => {
+    inFlightRequests--;
+  });
+}
+
+// Read endpoints pretty-print JSON for debugging with ?pretty=true or an X-Pretty: true header
+function wantsPrettyJson(req: Request): boolean {
+  if (req.method !== 'GET' && req.method !== 'HEAD') return false;
+  return new URL(req.url).searchParams.get('pretty') === 'true' || req.headers.get('x-pretty') === 'true';
+}
+
+async function prettyPrintResponse(res: Response): Promise<Response> {
+  if (!(res.headers.get('content-type') || '').startsWith('application/json')) {
+    return res;
+  }
+  
+  const body = JSON.stringify(await res.json(), null, 2);
+  const headers = new Headers(res.headers);
+  headers.delete('Content-Length');
+  return new Response(body, { status: res.status, statusText: res.statusText, headers });
+}
+
+// Gzip JSON responses at or above this size when the client accepts it; smaller bodies aren't worth it
+const COMPRESSION_THRESHOLD_BYTES = 1024;
+
+async function compressResponse(req: Request, res: Response): Promise<Response> {
+  const acceptEncoding = req.headers.get('accept-encoding') || '';
+  const contentType = res.headers.get('content-type') || '';
+  
+  if (!/\bgzip\b/.test(acceptEncoding) || res.headers.has('content-encoding') || !contentType.startsWith('application/json')) {
+    return res;
+  }
+  
+  const body = new Uint8Array(await res.arrayBuffer());
+  const headers = new Headers(res.headers);
+  headers.append('Vary', 'Accept-Encoding');
+  
+  if (body.byteLength < COMPRESSION_THRESHOLD_BYTES) {
+    return new Response(body, { status: res.status, statusText: res.statusText, headers });
+  }
+  
+  headers.set('Content-Encoding', 'gzip');
+  headers.delete('Content-Length');
+  return new Response(Bun.gzipSync(body), { status: res.status, statusText: res.statusText, headers });
+}
+
+// Drop the body for HEAD while keeping the Content-Length the GET would have sent
+async function toHeadResponse(res: Response): Promise<Response> {
+  const body = await res.arrayBuffer();
+  const headers = new Headers(res.headers);
+  headers.set('Content-Length', String(body.byteLength));
+  return new Response(null, { status: res.status, statusText: res.statusText, headers });
+}
+
+// Reuse the caller's x-request-id when it looks sane, otherwise mint one
+const REQUEST_ID_PATTERN = /^[A-Za-z0-9._:-]{1,128}$/;
+
+function resolveRequestId(req: Request): string {
+  const incoming = req.headers.get('x-request-id');
+  return incoming && REQUEST_ID_PATTERN.test(incoming) ? incoming : crypto.randomUUID();
+}
+
+// Bun.serve throws when the port is taken; turn that into one clear line instead of a stack trace
+function listenOrExit(listen: () => Server): Server {
+  try {
+    return listen();
+  } catch (error) {
+    const { code } = error as { code?: string };
+    if (code === 'EADDRINUSE') {
+      logger.error(`address ${config.HOST}:${config.PORT} already in use, is another instance running?`);
+      process.exit(1);
+    }
+    throw error;
+  }
+}
+
+// The full HTTP pipeline around handleRequest: metrics, HEAD, body logging, compression and the
+// access log. Exported so tests can drive it without listening on a port.
+export async function handleFetch(req: Request, server: Server): Promise<Response | undefined> {
+  // GET /ping - Cheapest possible liveness probe: answered before auth, metrics and logging
+  const path = routePath(new URL(req.url));
+  if (path === '/ping' && (req.method === 'GET' || req.method === 'HEAD')) {
+    return new Response(req.method === 'HEAD' ? null : 'pong', {
+      headers: { 'Content-Type': 'text/plain; charset=utf-8', 'Content-Length': '4' }
+    });
+  }
+  
+  const matched = path !== undefined ? matchEnabledRoute(path) : undefined;
+  const route = matched?.path ?? 'unmatched';
+  const start = performance.now();
+  const requestId = resolveRequestId(req);
+  const ctx: RequestContext = { requestId, clientIp: clientIp(req, server) };
+  recordRequest(req.method, route);
+  
+  // HEAD is answered by the GET handler with the body stripped and Content-Length kept.
+  // Streaming routes have no finite body to measure, so HEAD there gets the 405 path.
+  const isHead = req.method === 'HEAD' && !matched?.stream;
+  // Streaming routes are never body-logged: buffering them would hold the connection's output
+  const bodyLogRequest = config.DEBUG_BODY_LOG && !matched?.stream ? req.clone() : undefined;
+  const res = await handleWithConcurrencyLimit(isHead ? new Request(req, { method: 'GET' }) : req, server, ctx, matched);
+  const formatted = res && wantsPrettyJson(req) ? await prettyPrintResponse(res) : res;
+  if (bodyLogRequest) {
+    await logBodies(bodyLogRequest, formatted, requestId, ctx.timedOut);
+  }
+  let response = formatted ? await compressResponse(req, formatted) : formatted;
+  if (isHead && response) {
+    response = await toHeadResponse(response);
+  }
+  response?.headers.set('X-Request-Id', requestId);
+  const elapsedMs = performance.now() - start;
+  recordRequestDuration(route, elapsedMs / 1000);
+  // No response means the WebSocket upgrade went ahead
+  const status = response?.status ?? 101;
+  recordResponse(status);
+  
+  if (config.ACCESS_LOG) {
+    accessLogger.info('request completed', {
+      method: req.method,
+      path: new URL(req.url).pathname,
+      status,
+      latency_ms: Math.round(elapsedMs * 100) / 100,
+      client_ip: ctx.clientIp,
+      subject: ctx.claims?.sub,
+      request_id: requestId
+    });
+  }
+  return response;
+}
+
+// Only listen when run as the entry point; tests import this module for handleFetch
+const server = import.meta.main ? listenOrExit(() => Bun.serve({
+  port: config.PORT,
+  hostname: config.HOST,
+  maxRequestBodySize: config.MAX_BODY_BYTES,
+  // Bun (uWebSockets) already disables Nagle's algorithm on every socket, so only keep-alive is tunable
+  idleTimeout: config.IDLE_TIMEOUT_SECONDS,
+  fetch: handleFetch,
   
   websocket: {
     open(ws) {
-      console.log('WebSocket client connected');
-      wsClients.add(ws);
+      logger.info('WebSocket client connected');
+      
+      // A reconnecting dashboard passes ?since_id= with the last id it saw and gets the events
+      // it missed as ordinary 'event' messages. Replay and registration happen in the same
+      // synchronous step, so no event can be broadcast in between: no gap, no duplicate.
+      const { sinceId } = (ws.data ?? {}) as StreamSocketData;
+      if (sinceId !== undefined) {
+        for (const event of iterateEvents({ after_id: sinceId })) {
+          ws.send(JSON.stringify({ type: 'event', data: event }));
+        }
+      } else {
+        // Send recent events on connection
+        const events = getRecentEvents(50);
+        ws.send(JSON.stringify({ type: 'initial', data: events }));
+      }
       
-      // Send recent events on connection
-      const events = getRecentEvents(50);
-      ws.send(JSON.stringify({ type: 'initial', data: events }));
+      wsClients.add(ws);
     },
     
     message(ws, message) {
       // Handle any client messages if needed
-      console.log('Received message:', message);
+      logger.debug('Received WebSocket message', { message });
     },
     
     close(ws) {
-      console.log('WebSocket client disconnected');
+      logger.info('WebSocket client disconnected');
       wsClients.delete(ws);
     }
   }
-});
+})) : undefined;
+
+if (server) {
+  logger.info(`🚀 Server running on http://localhost:${server.port}${config.BASE_PATH}`);
+  logger.info(`📊 WebSocket endpoint: ws://localhost:${server.port}${config.BASE_PATH}/stream`);
+  logger.info(`📡 SSE endpoint: http://localhost:${server.port}${config.BASE_PATH}/events/stream`);
+  logger.info(`📮 POST events to: http://localhost:${server.port}${config.BASE_PATH}/events`);
+}
+
+// EVENT_TTL_SECONDS: sweep out expired events every minute (or every TTL, if shorter)
+const ttlSeconds = config.EVENT_TTL_SECONDS;
+const ttlSweepTimer = server && ttlSeconds
+  ? setInterval(() => {
+      try {
+        const evicted = evictExpiredEvents(ttlSeconds * 1000);
+        if (evicted > 0) {
+          logger.debug('Evicted expired events', { count: evicted, ttl_seconds: ttlSeconds });
+        }
+      } catch (error) {
+        logger.error('Failed to evict expired events', { error });
+      }
+    }, Math.min(ttlSeconds * 1000, 60000))
+  : undefined;
+
+// Graceful shutdown: stop accepting connections, let in-flight requests finish, then close the database
+let shuttingDown = false;
+
+async function shutdown(signal: string): Promise<void> {
+  if (shuttingDown) return;
+  shuttingDown = true;
+  
+  logger.info(`🛑 Received ${signal}, shutting down gracefully...`);
+  
+  clearInterval(ttlSweepTimer);
+  
+  wsClients.forEach(client => {
+    try {
+      client.close(1001, 'Server shutting down');
+    } catch (err) {
+      // Client already gone
+    }
+  });
+  wsClients.clear();
+  
+  sseClients.forEach(client => {
+    removeSseClient(client);
+    try {
+      client.controller.close();
+    } catch (err) {
+      // Stream already closed
+    }
+  });
+  
+  // Let in-flight requests (including ingestion) finish before flushing events to disk
+  await server?.stop();
+  
+  try {
+    closeDatabase();
+  } catch (error) {
+    logger.error('Failed to flush events to disk during shutdown', { error });
+  }
+  
+  logger.info('👋 Shutdown complete');
+  process.exit(0);
+}
 
-console.log(`🚀 Server running on http://localhost:${server.port}`);
-console.log(`📊 WebSocket endpoint: ws://localhost:${server.port}/stream`);
-console.log(`📮 POST events to: http://localhost:${server.port}/events`);
\ No newline at end of file
+if (server) {
+  process.on('SIGINT', () => shutdown('SIGINT'));
+  process.on('SIGTERM', () => shutdown('SIGTERM'));
+}
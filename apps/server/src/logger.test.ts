@@ -0,0 +1,53 @@
+import { describe, test, expect, spyOn } from 'bun:test';
+import { config } from './config';
+import { createLogger } from './logger';
+import { restoreConfigAfterEach } from './testHelpers';
+
+restoreConfigAfterEach();
+
+// Run `log` with console.log replaced, returning the arguments of every call
+function captureInfo(log: () => void): unknown[][] {
+  const spy = spyOn(console, 'log').mockImplementation(() => {});
+  try {
+    log();
+    return spy.mock.calls;
+  } finally {
+    spy.mockRestore();
+  }
+}
+
+describe('createLogger', () => {
+  test('text format writes the message, then the fields when there are any', () => {
+    config.LOG_LEVEL = 'info';
+    config.LOG_FORMAT = 'text';
+    const logger = createLogger('tests');
+    
+    const calls = captureInfo(() => {
+      logger.info('plain');
+      logger.info('with fields', { request_id: 'abc' });
+    });
+    
+    expect(calls).toEqual([['plain'], ['with fields', { request_id: 'abc' }]]);
+  });
+  
+  test('json format writes one object per line with level, target and the fields', () => {
+    config.LOG_LEVEL = 'info';
+    config.LOG_FORMAT = 'json';
+    const logger = createLogger('tests');
+    
+    const calls = captureInfo(() => logger.info('started', { port: 4000, error: new Error('boom') }));
+    
+    expect(calls).toHaveLength(1);
+    const entry = JSON.parse(String(calls[0]?.[0]));
+    expect(entry).toMatchObject({ level: 'info', target: 'tests', message: 'started', port: 4000 });
+    expect(entry.error.message).toBe('boom');
+    expect(Number.isNaN(Date.parse(entry.timestamp))).toBe(false);
+  });
+  
+  test('messages below LOG_LEVEL are dropped', () => {
+    config.LOG_LEVEL = 'warn';
+    const logger = createLogger('tests');
+    
+    expect(captureInfo(() => logger.info('hidden'))).toEqual([]);
+  });
+});
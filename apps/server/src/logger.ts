@@ -0,0 +1,72 @@
+import { config } from './config';
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug';
+export type LogFields = Record<string, unknown>;
+
+export interface Logger {
+  error(message: string, fields?: LogFields): void;
+  warn(message: string, fields?: LogFields): void;
+  info(message: string, fields?: LogFields): void;
+  debug(message: string, fields?: LogFields): void;
+}
+
+// Looked up on every write rather than captured here, so a replaced console method is used
+const consoleMethods: Record<LogLevel, 'error' | 'warn' | 'log' | 'debug'> = {
+  error: 'error',
+  warn: 'warn',
+  info: 'log',
+  debug: 'debug'
+};
+
+// Lower is more severe; a message is written when its level is at or above LOG_LEVEL
+const levelRank: Record<LogLevel, number> = {
+  error: 0,
+  warn: 1,
+  info: 2,
+  debug: 3
+};
+
+// Errors don't survive JSON.stringify, so flatten them first
+function serializeField(value: unknown): unknown {
+  if (value instanceof Error) {
+    return { name: value.name, message: value.message, stack: value.stack };
+  }
+  return value;
+}
+
+function write(target: string, level: LogLevel, message: string, fields?: LogFields): void {
+  if (levelRank[level] > levelRank[config.LOG_LEVEL]) return;
+  
+  const method = consoleMethods[level];
+  const out = (...args: unknown[]) => console[method](...args);
+  
+  if (config.LOG_FORMAT === 'json') {
+    const entry: LogFields = {
+      timestamp: new Date().toISOString(),
+      level,
+      target,
+      message
+    };
+    for (const [key, value] of Object.entries(fields ?? {})) {
+      entry[key] = serializeField(value);
+    }
+    out(JSON.stringify(entry));
+    return;
+  }
+  
+  if (fields && Object.keys(fields).length > 0) {
+    out(message, fields);
+  } else {
+    out(message);
+  }
+}
+
+// Create a logger that drops messages below LOG_LEVEL and formats the rest per LOG_FORMAT (text or json)
+export function createLogger(target: string): Logger {
+  return {
+    error: (message, fields) => write(target, 'error', message, fields),
+    warn: (message, fields) => write(target, 'warn', message, fields),
+    info: (message, fields) => write(target, 'info', message, fields),
+    debug: (message, fields) => write(target, 'debug', message, fields)
+  };
+}
@@ -0,0 +1,44 @@
+import { describe, test, expect } from 'bun:test';
+import { recordEventIngested, renderMetrics } from './metrics';
+
+// Current value of one events_total sample, 0 when the series hasn't been emitted yet
+function eventsTotal(agent: string, eventType: string): number {
+  const series = `observability_events_total{agent="${agent}",event_type="${eventType}"} `;
+  const line = renderMetrics({ websocket: 0, sse: 0 }).split('\n').find(line => line.startsWith(series));
+  return line ? Number(line.slice(series.length)) : 0;
+}
+
+describe('events_total', () => {
+  test('counts each agent separately', () => {
+    recordEventIngested('agent-a', 'PreToolUse');
+    recordEventIngested('agent-a', 'PreToolUse');
+    recordEventIngested('agent-b', 'PreToolUse');
+    
+    expect(eventsTotal('agent-a', 'PreToolUse')).toBe(2);
+    expect(eventsTotal('agent-b', 'PreToolUse')).toBe(1);
+  });
+  
+  test('event types outside KNOWN_EVENT_TYPES are counted as other', () => {
+    const before = eventsTotal('agent-c', 'other');
+    
+    recordEventIngested('agent-c', 'MadeUpHook');
+    
+    expect(eventsTotal('agent-c', 'other')).toBe(before + 1);
+    expect(renderMetrics({ websocket: 0, sse: 0 })).not.toContain('MadeUpHook');
+  });
+  
+  test('agents past the label cap are counted as other', () => {
+    for (let i = 0; i < 100; i++) {
+      recordEventIngested(`flood-${i}`, 'Stop');
+    }
+    const before = eventsTotal('other', 'Stop');
+    
+    recordEventIngested('one-too-many', 'Stop');
+    
+    expect(eventsTotal('other', 'Stop')).toBe(before + 1);
+    expect(renderMetrics({ websocket: 0, sse: 0 })).not.toContain('one-too-many');
+    // Agents that already have a label keep it
+    recordEventIngested('agent-a', 'Stop');
+    expect(eventsTotal('agent-a', 'Stop')).toBe(1);
+  });
+});
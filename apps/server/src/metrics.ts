@@ -0,0 +1,149 @@
+import { config } from './config';
+
+// In-process Prometheus metrics, rendered in the text exposition format at /metrics
+const METRIC_PREFIX = 'observability_';
+
+// source_app and hook_event_type come from clients, so their label values are bounded: event
+// types outside KNOWN_EVENT_TYPES and agents beyond the first MAX_AGENT_LABELS share 'other'
+const MAX_AGENT_LABELS = 100;
+const OTHER_LABEL = 'other';
+const knownEventTypes = new Set(config.KNOWN_EVENT_TYPES);
+const labelledAgents = new Set<string>();
+
+let requestsTotal = 0;
+let eventsIngestedTotal = 0;
+const eventsByAgent = new Map<string, { agent: string; eventType: string; count: number }>();
+const laggingSubscribersDropped = new Map<string, number>();
+const routeRequests = new Map<string, { method: string; route: string; count: number }>();
+const responsesByStatus = new Map<number, number>();
+
+// Request latency histogram buckets, in seconds
+const DURATION_BUCKETS = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1, 2.5, 5, 10];
+const routeDurations = new Map<string, { buckets: number[]; sum: number; count: number }>();
+
+function escapeLabel(value: string): string {
+  return value.replace(/\\/g, '\\\\').replace(/"/g, '\\"').replace(/\n/g, '\\n');
+}
+
+export function recordRequest(method: string, route: string): void {
+  requestsTotal++;
+  
+  const key = `${method} ${route}`;
+  const entry = routeRequests.get(key);
+  if (entry) {
+    entry.count++;
+  } else {
+    routeRequests.set(key, { method, route, count: 1 });
+  }
+}
+
+export function recordRequestDuration(route: string, seconds: number): void {
+  const histogram = routeDurations.get(route) ?? { buckets: DURATION_BUCKETS.map(() => 0), sum: 0, count: 0 };
+  routeDurations.set(route, histogram);
+  
+  // Buckets are stored cumulatively, as Prometheus expects
+  DURATION_BUCKETS.forEach((le, i) => {
+    if (seconds <= le) histogram.buckets[i] = (histogram.buckets[i] ?? 0) + 1;
+  });
+  histogram.sum += seconds;
+  histogram.count++;
+}
+
+// Counted by exact status and, at render time, by class (2xx, 4xx, ...) for error-rate alerts
+export function recordResponse(status: number): void {
+  responsesByStatus.set(status, (responsesByStatus.get(status) ?? 0) + 1);
+}
+
+// Counted per agent (source_app) and event type so one runaway agent stands out
+export function recordEventIngested(sourceApp: string, hookEventType: string): void {
+  eventsIngestedTotal++;
+  
+  if (!labelledAgents.has(sourceApp) && labelledAgents.size < MAX_AGENT_LABELS) {
+    labelledAgents.add(sourceApp);
+  }
+  const agent = labelledAgents.has(sourceApp) ? sourceApp : OTHER_LABEL;
+  const eventType = knownEventTypes.has(hookEventType) ? hookEventType : OTHER_LABEL;
+  
+  const key = `${agent}\n${eventType}`;
+  const entry = eventsByAgent.get(key);
+  if (entry) {
+    entry.count++;
+  } else {
+    eventsByAgent.set(key, { agent, eventType, count: 1 });
+  }
+}
+
+// A WebSocket or SSE subscriber was disconnected for falling too far behind the broadcast
+export function recordLaggingSubscriberDropped(transport: 'websocket' | 'sse'): void {
+  laggingSubscribersDropped.set(transport, (laggingSubscribersDropped.get(transport) ?? 0) + 1);
+}
+
+// Connected stream clients by transport, sampled at scrape time
+export interface SubscriberCounts {
+  websocket: number;
+  sse: number;
+}
+
+export function renderMetrics(subscribers: SubscriberCounts): string {
+  const lines: string[] = [];
+  
+  lines.push(`# HELP ${METRIC_PREFIX}requests_total Total HTTP requests received`);
+  lines.push(`# TYPE ${METRIC_PREFIX}requests_total counter`);
+  lines.push(`${METRIC_PREFIX}requests_total ${requestsTotal}`);
+  
+  lines.push(`# HELP ${METRIC_PREFIX}route_requests_total HTTP requests by method and route`);
+  lines.push(`# TYPE ${METRIC_PREFIX}route_requests_total counter`);
+  for (const { method, route, count } of routeRequests.values()) {
+    lines.push(`${METRIC_PREFIX}route_requests_total{method="${escapeLabel(method)}",route="${escapeLabel(route)}"} ${count}`);
+  }
+  
+  lines.push(`# HELP ${METRIC_PREFIX}request_duration_seconds HTTP request latency by route`);
+  lines.push(`# TYPE ${METRIC_PREFIX}request_duration_seconds histogram`);
+  for (const [route, histogram] of routeDurations) {
+    const label = `route="${escapeLabel(route)}"`;
+    DURATION_BUCKETS.forEach((le, i) => {
+      lines.push(`${METRIC_PREFIX}request_duration_seconds_bucket{${label},le="${le}"} ${histogram.buckets[i]}`);
+    });
+    lines.push(`${METRIC_PREFIX}request_duration_seconds_bucket{${label},le="+Inf"} ${histogram.count}`);
+    lines.push(`${METRIC_PREFIX}request_duration_seconds_sum{${label}} ${histogram.sum}`);
+    lines.push(`${METRIC_PREFIX}request_duration_seconds_count{${label}} ${histogram.count}`);
+  }
+  
+  lines.push(`# HELP ${METRIC_PREFIX}responses_total HTTP responses by status code`);
+  lines.push(`# TYPE ${METRIC_PREFIX}responses_total counter`);
+  const byClass = new Map<string, number>();
+  for (const [status, count] of [...responsesByStatus].sort(([a], [b]) => a - b)) {
+    lines.push(`${METRIC_PREFIX}responses_total{status="${status}"} ${count}`);
+    const statusClass = `${Math.floor(status / 100)}xx`;
+    byClass.set(statusClass, (byClass.get(statusClass) ?? 0) + count);
+  }
+  
+  lines.push(`# HELP ${METRIC_PREFIX}response_classes_total HTTP responses by status class (2xx, 4xx, 5xx, ...)`);
+  lines.push(`# TYPE ${METRIC_PREFIX}response_classes_total counter`);
+  for (const [statusClass, count] of byClass) {
+    lines.push(`${METRIC_PREFIX}response_classes_total{class="${statusClass}"} ${count}`);
+  }
+  
+  lines.push(`# HELP ${METRIC_PREFIX}events_ingested_total Hook events accepted by POST /events and /events/batch`);
+  lines.push(`# TYPE ${METRIC_PREFIX}events_ingested_total counter`);
+  lines.push(`${METRIC_PREFIX}events_ingested_total ${eventsIngestedTotal}`);
+  
+  lines.push(`# HELP ${METRIC_PREFIX}events_total Hook events ingested, by agent (source_app) and event type`);
+  lines.push(`# TYPE ${METRIC_PREFIX}events_total counter`);
+  for (const { agent, eventType, count } of eventsByAgent.values()) {
+    lines.push(`${METRIC_PREFIX}events_total{agent="${escapeLabel(agent)}",event_type="${escapeLabel(eventType)}"} ${count}`);
+  }
+  
+  lines.push(`# HELP ${METRIC_PREFIX}active_subscribers Connected WebSocket and SSE stream clients`);
+  lines.push(`# TYPE ${METRIC_PREFIX}active_subscribers gauge`);
+  lines.push(`${METRIC_PREFIX}active_subscribers{transport="websocket"} ${subscribers.websocket}`);
+  lines.push(`${METRIC_PREFIX}active_subscribers{transport="sse"} ${subscribers.sse}`);
+  
+  lines.push(`# HELP ${METRIC_PREFIX}lagging_subscribers_dropped_total Stream subscribers disconnected for not keeping up, by transport`);
+  lines.push(`# TYPE ${METRIC_PREFIX}lagging_subscribers_dropped_total counter`);
+  for (const transport of ['websocket', 'sse']) {
+    lines.push(`${METRIC_PREFIX}lagging_subscribers_dropped_total{transport="${transport}"} ${laggingSubscribersDropped.get(transport) ?? 0}`);
+  }
+  
+  return lines.join('\n') + '\n';
+}
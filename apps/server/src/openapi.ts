@@ -0,0 +1,517 @@
+import packageJson from '../package.json';
+import { config } from './config';
+
+// OpenAPI 3.0 description of the HTTP API, served at /openapi.json.
+// Hand-maintained: update alongside the handlers in index.ts.
+
+const jsonContent = (schema: object) => ({ 'application/json': { schema } });
+const ref = (name: string) => ({ $ref: `#/components/schemas/${name}` });
+
+const errorResponse = (description: string) => ({
+  description,
+  content: jsonContent(ref('Error'))
+});
+
+const limitParam = (defaultLimit: number) => ({
+  name: 'limit',
+  in: 'query',
+  schema: { type: 'integer', minimum: 1, default: defaultLimit }
+});
+
+export function buildOpenApiSpec(): object {
+  return {
+    openapi: '3.0.3',
+    info: {
+      title: 'Multi-Agent Observability Server',
+      version: packageJson.version,
+      description: 'Collects Claude Code hook events and streams them to dashboards.'
+    },
+    // Paths below are relative to BASE_PATH when the server is mounted at a subpath
+    ...(config.BASE_PATH ? { servers: [{ url: config.BASE_PATH }] } : {}),
+    components: {
+      securitySchemes: {
+        ApiKeyAuth: { type: 'apiKey', in: 'header', name: 'X-Api-Key' },
+        BearerAuth: { type: 'http', scheme: 'bearer', bearerFormat: 'JWT' }
+      },
+      schemas: {
+        Error: {
+          type: 'object',
+          required: ['error'],
+          properties: { error: { type: 'string' } }
+        },
+        Health: {
+          type: 'object',
+          required: ['status', 'timestamp', 'uptime_seconds', 'version'],
+          properties: {
+            status: { type: 'string', example: 'healthy' },
+            timestamp: { type: 'string', format: 'date-time' },
+            uptime_seconds: { type: 'integer' },
+            version: { type: 'string' }
+          }
+        },
+        BuildInfo: {
+          type: 'object',
+          required: ['version', 'git_sha', 'build_timestamp', 'bun_version'],
+          properties: {
+            version: { type: 'string' },
+            git_sha: { type: 'string', description: "'unknown' when it can't be determined" },
+            build_timestamp: { type: 'string', description: "BUILD_TIMESTAMP, or 'unknown'" },
+            bun_version: { type: 'string' }
+          }
+        },
+        Readiness: {
+          type: 'object',
+          required: ['status', 'timestamp', 'checks'],
+          properties: {
+            status: {
+              type: 'string',
+              enum: ['ready', 'degraded', 'not_ready'],
+              description: 'degraded means the event store is at MAX_EVENTS and evicting the oldest events'
+            },
+            timestamp: { type: 'string', format: 'date-time' },
+            event_buffer_used: { type: 'integer', description: 'Stored events; omitted when the database is unavailable' },
+            event_buffer_capacity: { type: 'integer', description: 'MAX_EVENTS; 0 means unbounded' },
+            checks: {
+              type: 'array',
+              items: {
+                type: 'object',
+                required: ['name', 'status'],
+                properties: {
+                  name: { type: 'string', example: 'database' },
+                  status: { type: 'string', enum: ['ok', 'failing'] },
+                  error: { type: 'string' }
+                }
+              }
+            }
+          }
+        },
+        HookEvent: {
+          type: 'object',
+          required: ['source_app', 'session_id', 'hook_event_type', 'payload'],
+          properties: {
+            id: { type: 'integer', readOnly: true },
+            source_app: { type: 'string' },
+            session_id: { type: 'string' },
+            hook_event_type: { type: 'string', example: 'PreToolUse' },
+            payload: { type: 'object', additionalProperties: true },
+            chat: { type: 'array', items: {} },
+            summary: { type: 'string' },
+            timestamp: { type: 'integer', description: 'Milliseconds since the Unix epoch; set by the server when omitted' },
+            tags: { type: 'array', items: { type: 'string' }, readOnly: true, description: 'Set via PATCH /events/{id}' },
+            note: { type: 'string', readOnly: true, description: 'Set via PATCH /events/{id}' }
+          }
+        },
+        EventCount: {
+          type: 'object',
+          required: ['count', 'capacity', 'evicted'],
+          properties: {
+            count: { type: 'integer' },
+            capacity: { type: 'integer', description: 'MAX_EVENTS; 0 means unbounded' },
+            evicted: { type: 'integer', description: 'Events evicted since startup' }
+          }
+        },
+        EventStats: {
+          type: 'object',
+          required: ['total', 'by_event_type', 'by_source_app', 'latest_timestamp'],
+          properties: {
+            total: { type: 'integer' },
+            by_event_type: { type: 'object', additionalProperties: { type: 'integer' } },
+            by_source_app: { type: 'object', additionalProperties: { type: 'integer' } },
+            latest_timestamp: { type: 'integer', nullable: true }
+          }
+        },
+        SessionSummary: {
+          type: 'object',
+          required: ['session_id', 'source_app', 'event_count', 'first_seen', 'last_seen'],
+          properties: {
+            session_id: { type: 'string' },
+            source_app: { type: 'string' },
+            event_count: { type: 'integer' },
+            first_seen: { type: 'integer' },
+            last_seen: { type: 'integer' }
+          }
+        },
+        FilterOptions: {
+          type: 'object',
+          properties: {
+            source_apps: { type: 'array', items: { type: 'string' } },
+            session_ids: { type: 'array', items: { type: 'string' } },
+            hook_event_types: { type: 'array', items: { type: 'string' } }
+          }
+        },
+        ApiResponse: {
+          type: 'object',
+          required: ['success'],
+          properties: {
+            success: { type: 'boolean' },
+            data: {},
+            error: { type: 'string' },
+            message: { type: 'string' }
+          }
+        }
+      }
+    },
+    security: [{ ApiKeyAuth: [] }, { BearerAuth: [] }],
+    paths: {
+      '/': {
+        get: {
+          summary: 'Service name, version and route index',
+          security: [],
+          responses: {
+            '200': {
+              description: 'API index',
+              content: jsonContent({
+                type: 'object',
+                properties: {
+                  name: { type: 'string' },
+                  version: { type: 'string' },
+                  endpoints: {
+                    type: 'array',
+                    items: {
+                      type: 'object',
+                      properties: { path: { type: 'string' }, methods: { type: 'array', items: { type: 'string' } } }
+                    }
+                  }
+                }
+              })
+            }
+          }
+        }
+      },
+      '/ping': {
+        get: {
+          summary: 'Minimal liveness probe; skips auth, metrics and logging',
+          security: [],
+          responses: { '200': { description: 'The literal body pong', content: { 'text/plain': { schema: { type: 'string', example: 'pong' } } } } }
+        }
+      },
+      '/health': {
+        get: {
+          summary: 'Liveness check (alias of /health/live)',
+          security: [],
+          responses: { '200': { description: 'Process is up', content: jsonContent(ref('Health')) } }
+        }
+      },
+      '/health/live': {
+        get: {
+          summary: 'Liveness check',
+          security: [],
+          responses: { '200': { description: 'Process is up', content: jsonContent(ref('Health')) } }
+        }
+      },
+      '/health/ready': {
+        get: {
+          summary: 'Readiness check',
+          security: [],
+          responses: {
+            '200': { description: 'Ready (or degraded) and serving', content: jsonContent(ref('Readiness')) },
+            '503': { description: 'Not ready', content: jsonContent(ref('Readiness')) }
+          }
+        }
+      },
+      '/version': {
+        get: {
+          summary: 'Build and runtime version information',
+          responses: { '200': { description: 'Build metadata', content: jsonContent(ref('BuildInfo')) } }
+        }
+      },
+      '/metrics': {
+        get: {
+          summary: 'Prometheus metrics',
+          responses: { '200': { description: 'Metrics in Prometheus text format', content: { 'text/plain': { schema: { type: 'string' } } } } }
+        }
+      },
+      '/admin/shutdown': {
+        post: {
+          summary: 'Gracefully shut the server down (requires ENABLE_ADMIN=true and authentication)',
+          responses: {
+            '202': {
+              description: 'Shutdown started',
+              content: jsonContent({ type: 'object', properties: { status: { type: 'string', example: 'shutting_down' } } })
+            },
+            '404': errorResponse('Admin endpoints are disabled')
+          }
+        }
+      },
+      '/debug/state': {
+        get: {
+          summary: 'Snapshot of internal counters (requires ENABLE_DEBUG=true)',
+          responses: {
+            '200': {
+              description: 'Current internal state',
+              content: jsonContent({
+                type: 'object',
+                properties: {
+                  events: {
+                    type: 'object',
+                    properties: {
+                      count: { type: 'integer' },
+                      capacity: { type: 'integer', description: 'MAX_EVENTS; 0 means unbounded' },
+                      evicted: { type: 'integer' }
+                    }
+                  },
+                  subscribers: {
+                    type: 'object',
+                    properties: { websocket: { type: 'integer' }, sse: { type: 'integer' } }
+                  },
+                  uptime_seconds: { type: 'integer' }
+                }
+              })
+            },
+            '404': errorResponse('Debug endpoints are disabled')
+          }
+        }
+      },
+      '/events': {
+        get: {
+          summary: 'Query stored events, newest first',
+          parameters: [
+            { name: 'source_app', in: 'query', schema: { type: 'string' } },
+            { name: 'session_id', in: 'query', schema: { type: 'string' } },
+            { name: 'hook_event_type', in: 'query', schema: { type: 'string' } },
+            { name: 'since', in: 'query', description: 'Only events at or after this RFC 3339 time', schema: { type: 'string', format: 'date-time' } },
+            { name: 'until', in: 'query', description: 'Only events before this RFC 3339 time', schema: { type: 'string', format: 'date-time' } },
+            limitParam(100)
+          ],
+          responses: {
+            '200': { description: 'Matching events', content: jsonContent({ type: 'array', items: ref('HookEvent') }) },
+            '400': errorResponse('Invalid query parameter (limit, since or until)')
+          }
+        },
+        post: {
+          summary: 'Ingest a hook event',
+          parameters: [
+            {
+              name: 'Idempotency-Key',
+              in: 'header',
+              description: 'Retries with the same key return the originally stored event instead of a duplicate',
+              schema: { type: 'string' }
+            }
+          ],
+          requestBody: { required: true, content: jsonContent(ref('HookEvent')) },
+          responses: {
+            '200': { description: 'Stored event', content: jsonContent(ref('HookEvent')) },
+            '400': errorResponse('Invalid or incomplete event'),
+            '422': errorResponse('hook_event_type not in KNOWN_EVENT_TYPES (only when STRICT_EVENTS is on)'),
+            '429': errorResponse('Rate limit exceeded')
+          }
+        }
+      },
+      '/events/batch': {
+        post: {
+          summary: 'Ingest several hook events atomically',
+          description: 'The body may be sent with Content-Encoding: gzip; the inflated size is held to MAX_BODY_BYTES.',
+          requestBody: { required: true, content: jsonContent({ type: 'array', maxItems: 1000, items: ref('HookEvent') }) },
+          responses: {
+            '200': {
+              description: 'Number of events stored',
+              content: jsonContent({ type: 'object', required: ['accepted'], properties: { accepted: { type: 'integer' } } })
+            },
+            '400': errorResponse('Not an array, or an event is incomplete'),
+            '413': errorResponse('Request body (or its inflated size) larger than MAX_BODY_BYTES'),
+            '415': errorResponse('Content-Encoding other than gzip'),
+            '422': errorResponse('More than 1000 events in the batch, an event payload over MAX_EVENT_PAYLOAD_BYTES, or an unknown hook_event_type under STRICT_EVENTS'),
+            '429': errorResponse('Rate limit exceeded')
+          }
+        }
+      },
+      '/events/{id}': {
+        parameters: [{ name: 'id', in: 'path', required: true, schema: { type: 'integer' } }],
+        patch: {
+          summary: 'Annotate an event; omitted fields are left as they are, a null note clears it',
+          requestBody: {
+            required: true,
+            content: jsonContent({
+              type: 'object',
+              properties: {
+                tags: { type: 'array', items: { type: 'string' } },
+                note: { type: 'string', nullable: true }
+              }
+            })
+          },
+          responses: {
+            '200': { description: 'Annotated event', content: jsonContent(ref('HookEvent')) },
+            '400': errorResponse('Invalid annotation'),
+            '404': errorResponse('No event with this id')
+          }
+        }
+      },
+      '/events/search': {
+        get: {
+          summary: 'Case-insensitive substring search across event payloads and types, newest first',
+          parameters: [
+            { name: 'q', in: 'query', required: true, schema: { type: 'string', minLength: 1 } },
+            limitParam(100)
+          ],
+          responses: {
+            '200': { description: 'Matching events', content: jsonContent({ type: 'array', items: ref('HookEvent') }) },
+            '400': errorResponse('Missing or empty q, or invalid limit')
+          }
+        }
+      },
+      '/events/count': {
+        get: {
+          summary: 'Stored event count against the retention cap',
+          responses: { '200': { description: 'Event count', content: jsonContent(ref('EventCount')) } }
+        }
+      },
+      '/events/stats': {
+        get: {
+          summary: 'Event counts grouped by event type and source app',
+          responses: { '200': { description: 'Event statistics', content: jsonContent(ref('EventStats')) } }
+        }
+      },
+      '/events/export': {
+        get: {
+          summary: 'Export stored events as newline-delimited JSON, oldest first',
+          description: 'Supports a single byte range (Range: bytes=start-end) for resuming interrupted downloads. Send the ETag of the first response in If-Range (or If-Match) so a resume never splices two different exports together.',
+          parameters: [
+            { name: 'session_id', in: 'query', schema: { type: 'string' } },
+            { name: 'Range', in: 'header', schema: { type: 'string', example: 'bytes=1024-' } },
+            { name: 'If-Range', in: 'header', description: 'Serve the range only while the export still has this ETag, otherwise the full body', schema: { type: 'string' } },
+            { name: 'If-Match', in: 'header', description: 'Fail with 412 unless the export still has this ETag', schema: { type: 'string' } }
+          ],
+          responses: {
+            '200': {
+              description: 'One HookEvent JSON object per line',
+              content: { 'application/x-ndjson': { schema: ref('HookEvent') } }
+            },
+            '206': {
+              description: 'The requested byte range of the export, described by Content-Range',
+              content: { 'application/x-ndjson': { schema: { type: 'string' } } }
+            },
+            '412': { description: 'If-Match does not match the current ETag of the export' },
+            '416': { description: 'Range starts beyond the end of the export' }
+          }
+        }
+      },
+      '/events/recent': {
+        get: {
+          summary: 'Most recent events, oldest first',
+          parameters: [limitParam(100)],
+          responses: { '200': { description: 'Recent events', content: jsonContent({ type: 'array', items: ref('HookEvent') }) } }
+        }
+      },
+      '/events/filter-options': {
+        get: {
+          summary: 'Distinct values available for filtering',
+          responses: { '200': { description: 'Filter options', content: jsonContent(ref('FilterOptions')) } }
+        }
+      },
+      '/events/stream': {
+        get: {
+          summary: 'Server-Sent Events stream of newly ingested events',
+          responses: { '200': { description: 'hook_event SSE events', content: { 'text/event-stream': { schema: { type: 'string' } } } } }
+        }
+      },
+      '/stream': {
+        get: {
+          summary: 'WebSocket stream of events (upgrade required)',
+          description: "Without since_id the first message is {type:'initial'} with the 50 most recent events. With since_id, every event after that id is replayed as {type:'event'} messages before live events follow.",
+          parameters: [{ name: 'since_id', in: 'query', schema: { type: 'integer', minimum: 0 } }],
+          responses: { '101': { description: 'Switching protocols' }, '400': errorResponse('Upgrade failed') }
+        }
+      },
+      '/sessions': {
+        get: {
+          summary: 'Recently active sessions, most recent first',
+          parameters: [limitParam(50)],
+          responses: { '200': { description: 'Session summaries', content: jsonContent({ type: 'array', items: ref('SessionSummary') }) } }
+        }
+      },
+      '/sessions/{session_id}/timeline': {
+        parameters: [{ name: 'session_id', in: 'path', required: true, schema: { type: 'string' } }],
+        get: {
+          summary: 'Events for one session in chronological order',
+          responses: {
+            '200': {
+              description: 'Session timeline; delta_ms is the time since the previous event',
+              content: jsonContent({
+                type: 'object',
+                properties: {
+                  session_id: { type: 'string' },
+                  events: {
+                    type: 'array',
+                    items: { allOf: [ref('HookEvent'), { type: 'object', properties: { delta_ms: { type: 'integer' } } }] }
+                  }
+                }
+              })
+            },
+            '400': errorResponse('session_id is not valid percent-encoding'),
+            '404': errorResponse('No events for this session')
+          }
+        }
+      },
+      '/api/themes': {
+        get: {
+          summary: 'Search themes',
+          parameters: [
+            { name: 'query', in: 'query', schema: { type: 'string' } },
+            { name: 'isPublic', in: 'query', schema: { type: 'boolean' } },
+            { name: 'authorId', in: 'query', schema: { type: 'string' } },
+            { name: 'sortBy', in: 'query', schema: { type: 'string', enum: ['name', 'created', 'updated', 'downloads', 'rating'] } },
+            { name: 'sortOrder', in: 'query', schema: { type: 'string', enum: ['asc', 'desc'] } },
+            { name: 'limit', in: 'query', schema: { type: 'integer' } },
+            { name: 'offset', in: 'query', schema: { type: 'integer' } }
+          ],
+          responses: { '200': { description: 'Matching themes', content: jsonContent(ref('ApiResponse')) } }
+        },
+        post: {
+          summary: 'Create a theme',
+          requestBody: { required: true, content: jsonContent({ type: 'object' }) },
+          responses: {
+            '201': { description: 'Theme created', content: jsonContent(ref('ApiResponse')) },
+            '400': { description: 'Validation failed', content: jsonContent(ref('ApiResponse')) }
+          }
+        }
+      },
+      '/api/themes/{id}': {
+        parameters: [{ name: 'id', in: 'path', required: true, schema: { type: 'string' } }],
+        get: {
+          summary: 'Get a theme',
+          responses: {
+            '200': { description: 'Theme', content: jsonContent(ref('ApiResponse')) },
+            '404': { description: 'Not found', content: jsonContent(ref('ApiResponse')) }
+          }
+        },
+        put: {
+          summary: 'Update a theme',
+          requestBody: { required: true, content: jsonContent({ type: 'object' }) },
+          responses: {
+            '200': { description: 'Theme updated', content: jsonContent(ref('ApiResponse')) },
+            '400': { description: 'Validation failed', content: jsonContent(ref('ApiResponse')) }
+          }
+        },
+        delete: {
+          summary: 'Delete a theme',
+          parameters: [{ name: 'authorId', in: 'query', schema: { type: 'string' } }],
+          responses: {
+            '200': { description: 'Theme deleted', content: jsonContent(ref('ApiResponse')) },
+            '403': { description: 'Not the author', content: jsonContent(ref('ApiResponse')) },
+            '404': { description: 'Not found', content: jsonContent(ref('ApiResponse')) }
+          }
+        }
+      },
+      '/api/themes/{id}/export': {
+        parameters: [{ name: 'id', in: 'path', required: true, schema: { type: 'string' } }],
+        get: {
+          summary: 'Export a theme as a downloadable JSON file',
+          responses: { '200': { description: 'Theme export', content: jsonContent({ type: 'object' }) } }
+        }
+      },
+      '/api/themes/import': {
+        post: {
+          summary: 'Import a theme export',
+          requestBody: { required: true, content: jsonContent({ type: 'object' }) },
+          responses: { '201': { description: 'Theme imported', content: jsonContent(ref('ApiResponse')) } }
+        }
+      },
+      '/api/themes/stats': {
+        get: {
+          summary: 'Theme statistics',
+          responses: { '200': { description: 'Statistics', content: jsonContent(ref('ApiResponse')) } }
+        }
+      }
+    }
+  };
+}
@@ -0,0 +1,94 @@
+import { describe, test, expect, spyOn } from 'bun:test';
+import { config } from './config';
+import { takeToken } from './rateLimit';
+import { restoreConfigAfterEach } from './testHelpers';
+
+restoreConfigAfterEach();
+
+describe('takeToken', () => {
+  test('a flood gets the burst through, then is throttled with a Retry-After', () => {
+    config.RATE_LIMIT_RPS = 1;
+    config.RATE_LIMIT_BURST = 3;
+    config.RATE_LIMIT_JITTER_SECONDS = 0;
+    
+    const results = Array.from({ length: 5 }, () => takeToken('flood', 1000));
+    
+    expect(results.slice(0, 3).every(result => result.allowed)).toBe(true);
+    expect(results[3]).toEqual({ allowed: false, retryAfterSeconds: 1 });
+    expect(results[4]?.allowed).toBe(false);
+  });
+  
+  test('tokens refill at RATE_LIMIT_RPS', () => {
+    config.RATE_LIMIT_RPS = 2;
+    config.RATE_LIMIT_BURST = 1;
+    
+    expect(takeToken('refill', 0).allowed).toBe(true);
+    expect(takeToken('refill', 100).allowed).toBe(false);
+    expect(takeToken('refill', 600).allowed).toBe(true);
+  });
+  
+  test('each client has its own bucket', () => {
+    config.RATE_LIMIT_RPS = 1;
+    config.RATE_LIMIT_BURST = 1;
+    
+    expect(takeToken('client-a', 0).allowed).toBe(true);
+    expect(takeToken('client-a', 0).allowed).toBe(false);
+    expect(takeToken('client-b', 0).allowed).toBe(true);
+  });
+  
+  test('everything is allowed when RATE_LIMIT_RPS is unset', () => {
+    config.RATE_LIMIT_RPS = undefined;
+    
+    for (let i = 0; i < 100; i++) {
+      expect(takeToken('unlimited', 0).allowed).toBe(true);
+    }
+  });
+});
+
+describe('Retry-After jitter', () => {
+  test('stays within [base, base + RATE_LIMIT_JITTER_SECONDS]', () => {
+    config.RATE_LIMIT_RPS = 1;
+    config.RATE_LIMIT_BURST = 1;
+    config.RATE_LIMIT_JITTER_SECONDS = 3;
+    
+    takeToken('jitter-range', 0);
+    for (let i = 0; i < 200; i++) {
+      const result = takeToken('jitter-range', 0);
+      if (result.allowed) throw new Error('expected the bucket to stay empty');
+      expect(result.retryAfterSeconds).toBeGreaterThanOrEqual(1);
+      expect(result.retryAfterSeconds).toBeLessThanOrEqual(4);
+      expect(Number.isInteger(result.retryAfterSeconds)).toBe(true);
+    }
+  });
+  
+  test('reaches both ends of the range', () => {
+    config.RATE_LIMIT_RPS = 1;
+    config.RATE_LIMIT_BURST = 1;
+    config.RATE_LIMIT_JITTER_SECONDS = 3;
+    takeToken('jitter-ends', 0);
+    
+    const random = spyOn(Math, 'random');
+    try {
+      random.mockReturnValue(0);
+      expect(takeToken('jitter-ends', 0)).toEqual({ allowed: false, retryAfterSeconds: 1 });
+      random.mockReturnValue(0.999);
+      expect(takeToken('jitter-ends', 0)).toEqual({ allowed: false, retryAfterSeconds: 4 });
+    } finally {
+      random.mockRestore();
+    }
+  });
+  
+  test('RATE_LIMIT_JITTER_SECONDS=0 gives the exact wait', () => {
+    config.RATE_LIMIT_RPS = 1;
+    config.RATE_LIMIT_BURST = 1;
+    config.RATE_LIMIT_JITTER_SECONDS = 0;
+    takeToken('no-jitter', 0);
+    
+    const random = spyOn(Math, 'random').mockReturnValue(0.999);
+    try {
+      expect(takeToken('no-jitter', 0)).toEqual({ allowed: false, retryAfterSeconds: 1 });
+    } finally {
+      random.mockRestore();
+    }
+  });
+});
@@ -0,0 +1,65 @@
+import { config } from './config';
+
+// Token bucket per client: holds up to `burst` tokens, refilled at RATE_LIMIT_RPS per second
+interface Bucket {
+  tokens: number;
+  updatedAt: number;
+}
+
+export type RateLimitResult =
+  | { allowed: true }
+  | { allowed: false; retryAfterSeconds: number };
+
+const buckets = new Map<string, Bucket>();
+
+// How often idle buckets are swept out of the map
+const CLEANUP_INTERVAL_MS = 60000;
+
+function burstSize(rps: number): number {
+  return config.RATE_LIMIT_BURST ?? Math.max(1, Math.ceil(rps));
+}
+
+export function isRateLimitEnabled(): boolean {
+  return config.RATE_LIMIT_RPS !== undefined;
+}
+
+// Take one token for `key` (the client IP), refilling for the time elapsed since its last request
+export function takeToken(key: string, now: number = Date.now()): RateLimitResult {
+  const rps = config.RATE_LIMIT_RPS;
+  if (rps === undefined) {
+    return { allowed: true };
+  }
+  
+  const burst = burstSize(rps);
+  const bucket = buckets.get(key) ?? { tokens: burst, updatedAt: now };
+  bucket.tokens = Math.min(burst, bucket.tokens + ((now - bucket.updatedAt) / 1000) * rps);
+  bucket.updatedAt = now;
+  buckets.set(key, bucket);
+  
+  if (bucket.tokens >= 1) {
+    bucket.tokens -= 1;
+    return { allowed: true };
+  }
+  
+  // Jitter spreads out the retries of clients that were throttled at the same moment
+  const jitterSeconds = Math.floor(Math.random() * (config.RATE_LIMIT_JITTER_SECONDS + 1));
+  return { allowed: false, retryAfterSeconds: Math.ceil((1 - bucket.tokens) / rps) + jitterSeconds };
+}
+
+// A bucket that has been idle long enough to refill completely is the same as no
+// bucket at all, so drop it to keep the map from growing with every client ever seen
+function pruneIdleBuckets(now: number = Date.now()): void {
+  const rps = config.RATE_LIMIT_RPS;
+  if (rps === undefined) return;
+  
+  const refillMs = (burstSize(rps) / rps) * 1000;
+  for (const [key, bucket] of buckets) {
+    if (now - bucket.updatedAt >= refillMs) {
+      buckets.delete(key);
+    }
+  }
+}
+
+if (isRateLimitEnabled()) {
+  setInterval(pruneIdleBuckets, CLEANUP_INTERVAL_MS).unref();
+}
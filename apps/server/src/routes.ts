@@ -0,0 +1,45 @@
+// Known HTTP routes, used to label metrics without unbounded cardinality.
+// Keep in sync with the handlers in index.ts; more specific paths go first.
+export interface RouteDefinition {
+  path: string;
+  pattern: RegExp;
+  methods: string[];
+  // Streaming responses; these don't get HEAD handling and answer HEAD with 405
+  stream?: boolean;
+}
+
+export const routes: RouteDefinition[] = [
+  { path: '/', pattern: /^\/$/, methods: ['GET'] },
+  { path: '/ping', pattern: /^\/ping$/, methods: ['GET'] },
+  { path: '/health', pattern: /^\/health$/, methods: ['GET'] },
+  { path: '/health/live', pattern: /^\/health\/live$/, methods: ['GET'] },
+  { path: '/health/ready', pattern: /^\/health\/ready$/, methods: ['GET'] },
+  { path: '/version', pattern: /^\/version$/, methods: ['GET'] },
+  { path: '/metrics', pattern: /^\/metrics$/, methods: ['GET'] },
+  { path: '/openapi.json', pattern: /^\/openapi\.json$/, methods: ['GET'] },
+  { path: '/docs', pattern: /^\/docs$/, methods: ['GET'] },
+  { path: '/admin/shutdown', pattern: /^\/admin\/shutdown$/, methods: ['POST'] },
+  { path: '/debug/state', pattern: /^\/debug\/state$/, methods: ['GET'] },
+  { path: '/events', pattern: /^\/events$/, methods: ['GET', 'POST'] },
+  { path: '/events/batch', pattern: /^\/events\/batch$/, methods: ['POST'] },
+  { path: '/events/search', pattern: /^\/events\/search$/, methods: ['GET'] },
+  { path: '/events/count', pattern: /^\/events\/count$/, methods: ['GET'] },
+  { path: '/events/stats', pattern: /^\/events\/stats$/, methods: ['GET'] },
+  { path: '/events/export', pattern: /^\/events\/export$/, methods: ['GET'], stream: true },
+  { path: '/events/stream', pattern: /^\/events\/stream$/, methods: ['GET'], stream: true },
+  { path: '/events/filter-options', pattern: /^\/events\/filter-options$/, methods: ['GET'] },
+  { path: '/events/recent', pattern: /^\/events\/recent$/, methods: ['GET'] },
+  { path: '/events/:id', pattern: /^\/events\/\d+$/, methods: ['PATCH'] },
+  { path: '/sessions', pattern: /^\/sessions$/, methods: ['GET'] },
+  { path: '/sessions/:session_id/timeline', pattern: /^\/sessions\/[^\/]+\/timeline$/, methods: ['GET'] },
+  { path: '/api/themes', pattern: /^\/api\/themes$/, methods: ['GET', 'POST'] },
+  { path: '/api/themes/import', pattern: /^\/api\/themes\/import$/, methods: ['POST'] },
+  { path: '/api/themes/stats', pattern: /^\/api\/themes\/stats$/, methods: ['GET'] },
+  { path: '/api/themes/:id/export', pattern: /^\/api\/themes\/[^\/]+\/export$/, methods: ['GET'] },
+  { path: '/api/themes/:id', pattern: /^\/api\/themes\/[^\/]+$/, methods: ['GET', 'PUT', 'DELETE'] },
+  { path: '/stream', pattern: /^\/stream$/, methods: ['GET'], stream: true },
+];
+
+export function matchRoute(pathname: string): RouteDefinition | undefined {
+  return routes.find(route => route.pattern.test(pathname));
+}
@@ -0,0 +1,56 @@
+// Fixtures shared by the test files
+import { afterEach } from 'bun:test';
+import { config } from './config';
+import type { HookEvent } from './types';
+
+// A complete event as a hook script would send it
+export function hookEvent(overrides: Partial<HookEvent> = {}): HookEvent {
+  return {
+    source_app: 'tests',
+    session_id: 'session-1',
+    hook_event_type: 'PreToolUse',
+    payload: { tool_name: 'Bash' },
+    ...overrides
+  };
+}
+
+// Tests change config in place; put the loaded values back after each one
+export function restoreConfigAfterEach(): void {
+  const originalConfig = { ...config };
+  afterEach(() => {
+    Object.assign(config, originalConfig);
+  });
+}
+
+// A local HTTP server standing in for FORWARD_WEBHOOK_URL. It records every JSON body it
+// receives and answers with `status`, or never answers when `status` is 'hang'.
+export function mockWebhook(status: number | 'hang' = 200): { url: string; received: unknown[]; stop: () => void } {
+  const received: unknown[] = [];
+  const server = Bun.serve({
+    port: 0,
+    hostname: '127.0.0.1',
+    async fetch(req) {
+      received.push(await req.json());
+      if (status === 'hang') {
+        return new Promise<Response>(() => {});
+      }
+      return new Response(null, { status });
+    }
+  });
+  return {
+    url: `http://127.0.0.1:${server.port}/hook`,
+    received,
+    stop: () => server.stop(true)
+  };
+}
+
+// Poll until `done` holds, failing the test if it doesn't within `timeoutMs`
+export async function waitFor(done: () => boolean, timeoutMs: number = 1000): Promise<void> {
+  const deadline = Date.now() + timeoutMs;
+  while (!done()) {
+    if (Date.now() > deadline) {
+      throw new Error(`condition not met within ${timeoutMs}ms`);
+    }
+    await Bun.sleep(5);
+  }
+}
@@ -0,0 +1,27 @@
+// Preloaded by `bun test` (see bunfig.toml). config.ts reads the environment once, when it is
+// first imported, so this is the one place tests can pick their settings: an in-memory database
+// instead of events.db, and none of the developer's .env switches leaking into the results.
+process.env.NODE_ENV = 'test';
+process.env.DATABASE_PATH = ':memory:';
+process.env.LOG_LEVEL = 'error';
+
+for (const key of [
+  'BASE_PATH',
+  'AUTH_MODE',
+  'API_KEY',
+  'JWT_SECRET',
+  'RATE_LIMIT_RPS',
+  'RATE_LIMIT_BURST',
+  'MAX_CONNECTIONS',
+  'MAX_EVENTS',
+  'EVENT_TTL_SECONDS',
+  'EVENT_ARCHIVE_FILE',
+  'FORWARD_WEBHOOK_URL',
+  'STRICT_EVENTS',
+  'ENABLE_ADMIN',
+  'ENABLE_DEBUG',
+  'DEBUG_BODY_LOG',
+  'ACCESS_LOG'
+]) {
+  delete process.env[key];
+}
@@ -7,6 +7,9 @@ import {
   incrementThemeDownloadCount 
 } from './db';
 import type { Theme, ThemeSearchQuery, ThemeValidationError, ApiResponse } from './types';
+import { createLogger } from './logger';
+
+const logger = createLogger('theme');
 
 // Utility functions
 function generateId(): string {
@@ -177,7 +180,7 @@ export async function createTheme(themeData: any): Promise<ApiResponse<Theme>> {
       message: 'Theme created successfully'
     };
   } catch (error) {
-    console.error('Error creating theme:', error);
+    logger.error('Error creating theme', { error });
     return {
       success: false,
       error: 'Internal server error'
@@ -232,7 +235,7 @@ export async function updateThemeById(id: string, updates: any): Promise<ApiResp
       message: 'Theme updated successfully'
     };
   } catch (error) {
-    console.error('Error updating theme:', error);
+    logger.error('Error updating theme', { error });
     return {
       success: false,
       error: 'Internal server error'
@@ -261,7 +264,7 @@ export async function getThemeById(id: string): Promise<ApiResponse<Theme>> {
       data: theme
     };
   } catch (error) {
-    console.error('Error getting theme:', error);
+    logger.error('Error getting theme', { error });
     return {
       success: false,
       error: 'Internal server error'
@@ -284,7 +287,7 @@ export async function searchThemes(query: ThemeSearchQuery): Promise<ApiResponse
       data: themes
     };
   } catch (error) {
-    console.error('Error searching themes:', error);
+    logger.error('Error searching themes', { error });
     return {
       success: false,
       error: 'Internal server error'
@@ -325,7 +328,7 @@ export async function deleteThemeById(id: string, authorId?: string): Promise<Ap
       message: 'Theme deleted successfully'
     };
   } catch (error) {
-    console.error('Error deleting theme:', error);
+    logger.error('Error deleting theme', { error });
     return {
       success: false,
       error: 'Internal server error'
@@ -366,7 +369,7 @@ export async function exportThemeById(id: string): Promise<ApiResponse<any>> {
       data: exportData
     };
   } catch (error) {
-    console.error('Error exporting theme:', error);
+    logger.error('Error exporting theme', { error });
     return {
       success: false,
       error: 'Internal server error'
@@ -392,7 +395,7 @@ export async function importTheme(importData: any, authorId?: string): Promise<A
     
     return await createTheme(themeData);
   } catch (error) {
-    console.error('Error importing theme:', error);
+    logger.error('Error importing theme', { error });
     return {
       success: false,
       error: 'Internal server error'
@@ -421,7 +424,7 @@ export async function getThemeStats(): Promise<ApiResponse<any>> {
       data: stats
     };
   } catch (error) {
-    console.error('Error getting theme stats:', error);
+    logger.error('Error getting theme stats', { error });
     return {
       success: false,
       error: 'Internal server error'
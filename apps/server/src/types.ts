@@ -7,6 +7,51 @@ export interface HookEvent {
   chat?: any[];
   summary?: string;
   timestamp?: number;
+  // Annotations added after ingestion via PATCH /events/:id
+  tags?: string[];
+  note?: string;
+}
+
+export interface EventAnnotation {
+  tags?: string[];
+  note?: string | null;
+}
+
+export interface EventFilter {
+  source_app?: string;
+  session_id?: string;
+  hook_event_type?: string;
+  // Case-insensitive substring match against the payload JSON and hook_event_type
+  search?: string;
+  // Millisecond timestamps: since is inclusive, until is exclusive
+  since?: number;
+  until?: number;
+  limit?: number;
+}
+
+export interface SessionSummary {
+  session_id: string;
+  source_app: string;
+  event_count: number;
+  first_seen: number;
+  last_seen: number;
+}
+
+export interface TimelineEntry extends HookEvent {
+  delta_ms: number;
+}
+
+export interface EventStats {
+  total: number;
+  by_event_type: Record<string, number>;
+  by_source_app: Record<string, number>;
+  latest_timestamp: number | null;
+}
+
+export interface ReadinessCheck {
+  name: string;
+  status: 'ok' | 'failing';
+  error?: string;
 }
 
 export interface FilterOptions {
@@ -0,0 +1,31 @@
+import packageJson from '../package.json';
+
+// Build metadata served at /version. Bun runs the sources directly, so there is no
+// compile step to stamp these in: deployments pass GIT_SHA / BUILD_TIMESTAMP, and a
+// checkout falls back to asking git.
+export interface BuildInfo {
+  version: string;
+  git_sha: string;
+  build_timestamp: string;
+  bun_version: string;
+}
+
+function resolveGitSha(): string {
+  if (process.env.GIT_SHA) return process.env.GIT_SHA;
+  
+  try {
+    const result = Bun.spawnSync(['git', 'rev-parse', 'HEAD'], { cwd: import.meta.dir, stderr: 'ignore' });
+    const sha = result.stdout.toString().trim();
+    return result.success && sha ? sha : 'unknown';
+  } catch (error) {
+    // git isn't installed
+    return 'unknown';
+  }
+}
+
+export const buildInfo: BuildInfo = {
+  version: packageJson.version || 'unknown',
+  git_sha: resolveGitSha(),
+  build_timestamp: process.env.BUILD_TIMESTAMP || 'unknown',
+  bun_version: Bun.version
+};